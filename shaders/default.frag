@@ -6,22 +6,39 @@ layout(location = 2) in vec4 bitangent;
 layout(location = 3) in vec2 uv;
 layout(location = 4) in vec3 world_normal;
 layout(location = 5) in vec3 coord;
+layout(location = 6) in vec4 vertex_color;
+layout(location = 7) in vec2 uv1;
 
 out vec4 color;
 
+uniform vec4 u_object_color = vec4(1,1,1,1.0); // @internal
+
+// 0: object color, 1: vertex color, 2: uv set 0, 3: uv set 1, 4: normal
+uniform int u_color_source = 0; // @range(0, 4) @internal
+
 vec3 light = vec3(0.33, 0.33, 0.33);
 vec3 light_color = vec3(1,1,1);
 float calculate_diffuse(vec3 L, vec3 N){
     return dot(N,L);
 }
 
+vec3 object_color() {
+    switch (u_color_source) {
+        case 1: return vertex_color.rgb;
+        case 2: return vec3(fract(uv), 0.0);
+        case 3: return vec3(fract(uv1), 0.0);
+        case 4: return normalize(world_normal) * 0.5 + 0.5;
+        default: return u_object_color.rgb;
+    }
+}
+
 void main() {
     float strength = 0.05;
     vec3 ambient = strength * light_color;
 
     vec3 L = normalize(light);
-    float D = clamp(calculate_diffuse(L, world_normal.xyz),0.0,1.0);
-    vec3 object = vec3(1,1,1);
+    float D = clamp(calculate_diffuse(L, normalize(world_normal.xyz)),0.0,1.0);
+    vec3 object = object_color();
 
     vec3 final_color = ambient*object + D*object;
     color = vec4(final_color.xyz, 1.0);
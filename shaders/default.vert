@@ -5,6 +5,8 @@ layout(location = 1) in vec4 normal;
 layout(location = 2) in vec4 tangent;
 layout(location = 3) in vec4 bitangent;
 layout(location = 4) in vec2 uv;
+layout(location = 5) in vec2 uv1;
+layout(location = 6) in vec4 vertex_color;
 
 layout(location = 0) out vec4 out_normal;
 layout(location = 1) out vec4 out_tangent;
@@ -12,9 +14,14 @@ layout(location = 2) out vec4 out_bitangent;
 layout(location = 3) out vec2 out_uv;
 layout(location = 4) out vec3 out_world_normal;
 layout(location = 5) out vec3 out_coord;
+layout(location = 6) out vec4 out_color;
+layout(location = 7) out vec2 out_uv1;
 
-uniform mat4 projection;
-uniform mat4 model;
+uniform mat4 view_projection; // @internal
+uniform mat4 model; // @internal
+
+// Inverse transpose of the model matrix, keeps normals correct under non-uniform scaling
+uniform mat3 normal_matrix; // @internal
 
 // FUTURE: https://learnopengl.com/Lighting/Basic-Lighting
 void main() {
@@ -22,10 +29,12 @@ void main() {
     out_tangent = tangent;
     out_bitangent = bitangent;
     out_uv = uv;
-    out_world_normal = mat3(model) * normal.xyz;
+    out_uv1 = uv1;
+    out_color = vertex_color;
+    out_world_normal = normalize(normal_matrix * normal.xyz);
 
     // Output hardware position
-    vec4 pos = projection * position;
+    vec4 pos = view_projection * model * position;
     gl_Position = pos;
 
-}
\ No newline at end of file
+}
@@ -6,38 +6,69 @@ layout(location = 2) in vec4 bitangent;
 layout(location = 3) in vec2 uv;
 layout(location = 4) in vec3 world_normal;
 layout(location = 5) in vec3 coord;
+layout(location = 6) in vec4 vertex_color;
+layout(location = 7) in vec2 uv1;
+layout(location = 8) noperspective in vec3 edge_along;
 
 out vec4 color;
 
-uniform float u_line_thickness = 0.02;
-uniform float u_falloff = 0.003;
+uniform float u_line_thickness = 0.02; // @range(0.001, 1.0) @internal
+uniform float u_falloff = 0.003; // @range(0.0, 0.05) @internal
 
-uniform vec4 u_object_color = vec4(1,1,1,1.0);
-uniform vec4 u_wireframe_color = vec4(0,0,0,1.0);
+uniform vec4 u_object_color = vec4(1,1,1,1.0); // @internal
+uniform vec4 u_wireframe_color = vec4(0,0,0,1.0); // @internal
+// x: pattern, y: dash length, z: gap length, w: phase, lengths in pixels
+uniform vec4 u_stroke = vec4(0.0, 8.0, 4.0, 0.0); // @internal
+
+// 0: object color, 1: vertex color, 2: uv set 0, 3: uv set 1, 4: normal
+uniform int u_color_source = 0; // @range(0, 4) @internal
 
 uniform vec3 light = vec3(0.33, 0.33, 0.33);
 uniform vec3 light_color = vec3(1,1,1);
-uniform float ambient_strength = 0.05;
+uniform float ambient_strength = 0.05; // @range(0.0, 1.0)
+
+#include "stroke_pattern.glsl"
+
+// Length of the dots in pixels
+const float DOT_LENGTH = 2.0;
+
 float calculate_diffuse(vec3 L, vec3 N){
     return dot(N,L);
 }
 
+vec3 object_color() {
+    switch (u_color_source) {
+        case 1: return vertex_color.rgb;
+        case 2: return vec3(fract(uv), 0.0);
+        case 3: return vec3(fract(uv1), 0.0);
+        case 4: return normalize(world_normal) * 0.5 + 0.5;
+        default: return u_object_color.rgb;
+    }
+}
+
 void main() {
     vec3 ambient = ambient_strength * light_color;
 
     vec3  L = normalize(light);
-    float D = clamp(calculate_diffuse(L, world_normal.xyz),0.0,1.0);
+    float D = clamp(calculate_diffuse(L, normalize(world_normal.xyz)),0.0,1.0);
 
     // Calculate the final "lit" color
-    vec3 object = u_object_color.rgb;
+    vec3 object = object_color();
 
     // Get the minimum distance
     float d = min(coord.x, min(coord.y, coord.z));
     d = smoothstep(u_line_thickness, u_line_thickness + u_falloff, d);
-    float wireframe_blend = 1.0 - d;
-    float alpha_blend = u_wireframe_color.a;
 
-    object = mix(object, u_wireframe_color.rgb, (1.0 - d)*alpha_blend);
-    color = vec4(ambient*object + D*object, 1.0);
+    // The closest edge decides where along the stroke pattern we are
+    float along = coord.x <= coord.y && coord.x <= coord.z ? edge_along.x : (coord.y <= coord.z ? edge_along.y : edge_along.z);
+    d = max(d, 1.0 - clamp(0.5 - pattern_distance(along, u_stroke, DOT_LENGTH), 0.0, 1.0));
+    // Coverage of the line, the line color alpha makes it partially transparent
+    float coverage = (1.0 - d) * u_wireframe_color.a;
+
+    object = mix(object, u_wireframe_color.rgb, coverage);
+
+    // The fill alpha shows where the line doesn't cover the pixel. With alpha-to-coverage the
+    // alpha becomes the sample coverage, so a transparent fill leaves anti-aliased lines only.
+    color = vec4(ambient*object + D*object, mix(u_object_color.a, 1.0, coverage));
 
 }
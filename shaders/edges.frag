@@ -0,0 +1,54 @@
+#version 450 core
+
+out vec4 color;
+
+// Normals and linear depth written by gbuffer.frag
+uniform sampler2D u_gbuffer; // @internal
+
+// 0: Sobel, 1: Roberts cross
+uniform int u_operator = 0; // @range(0, 1) @internal
+// Depth difference relative to the depth of the pixel
+uniform float u_depth_threshold = 0.05; // @range(0.0, 1.0) @internal
+// Difference between the normals of neighbouring pixels
+uniform float u_normal_threshold = 0.5; // @range(0.0, 2.0) @internal
+// Distance between the filter taps in pixels, widens the lines
+uniform float u_line_thickness = 1.0; // @range(1.0, 8.0) @internal
+uniform vec4 u_wireframe_color = vec4(0,0,0,1.0); // @internal
+
+vec4 fetch(ivec2 offset) {
+    ivec2 size = textureSize(u_gbuffer, 0);
+    ivec2 p = ivec2(gl_FragCoord.xy) + offset * max(int(u_line_thickness + 0.5), 1);
+    return texelFetch(u_gbuffer, clamp(p, ivec2(0), size - 1), 0);
+}
+
+// Returns the depth (x) and normal (y) gradient magnitudes
+vec2 sobel() {
+    const float kx[9] = float[](-1, 0, 1, -2, 0, 2, -1, 0, 1);
+    const float ky[9] = float[](-1, -2, -1, 0, 0, 0, 1, 2, 1);
+
+    vec4 gx = vec4(0.0);
+    vec4 gy = vec4(0.0);
+    for (int i = 0; i < 9; ++i) {
+        vec4 s = fetch(ivec2(i % 3 - 1, i / 3 - 1));
+        gx += kx[i] * s;
+        gy += ky[i] * s;
+    }
+    return vec2(length(vec2(gx.w, gy.w)), sqrt(dot(gx.xyz, gx.xyz) + dot(gy.xyz, gy.xyz)));
+}
+
+vec2 roberts() {
+    vec4 d0 = fetch(ivec2(0, 0)) - fetch(ivec2(1, 1));
+    vec4 d1 = fetch(ivec2(1, 0)) - fetch(ivec2(0, 1));
+    return vec2(length(vec2(d0.w, d1.w)), sqrt(dot(d0.xyz, d0.xyz) + dot(d1.xyz, d1.xyz)));
+}
+
+void main() {
+    vec2 gradient = u_operator == 1 ? roberts() : sobel();
+    float depth = max(fetch(ivec2(0)).w, 1e-4);
+
+    float depth_edge = step(u_depth_threshold, gradient.x / depth);
+    float normal_edge = step(u_normal_threshold, gradient.y);
+    float edge = max(depth_edge, normal_edge);
+
+    color = vec4(u_wireframe_color.rgb, u_wireframe_color.a * edge);
+}
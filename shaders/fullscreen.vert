@@ -0,0 +1,7 @@
+#version 450 core
+
+// Full screen triangle generated from the vertex index, no vertex data needed
+void main() {
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
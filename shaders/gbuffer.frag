@@ -0,0 +1,16 @@
+#version 450 core
+
+layout(location = 4) in vec3 world_normal;
+
+// xyz: world space normal, w: linear view depth
+out vec4 gbuffer;
+
+// Clip planes of the camera projection
+uniform float u_near = 0.01; // @internal
+uniform float u_far = 1000.0; // @internal
+
+void main() {
+    float z = gl_FragCoord.z * 2.0 - 1.0;
+    float linear_depth = (2.0 * u_near * u_far) / (u_far + u_near - z * (u_far - u_near));
+    gbuffer = vec4(normalize(world_normal), linear_depth);
+}
@@ -0,0 +1,45 @@
+#version 450 core
+
+layout(location = 0) in vec4 normal;
+layout(location = 1) in vec4 tangent;
+layout(location = 2) in vec4 bitangent;
+layout(location = 3) in vec2 uv;
+layout(location = 4) in vec3 world_normal;
+layout(location = 5) in vec3 coord;
+
+out vec4 color;
+
+// One value in [0,1] per triangle, indexed with the primitive id
+layout(std430, binding = 0) readonly buffer TriangleMetrics {
+    float values[];
+};
+
+uniform float u_line_thickness = 0.02; // @internal
+uniform float u_falloff = 0.003; // @internal
+uniform vec4 u_wireframe_color = vec4(0,0,0,1.0); // @internal
+
+uniform vec3 light = vec3(0.33, 0.33, 0.33);
+uniform float ambient_strength = 0.3; // @range(0.0, 1.0)
+
+// Blue (good) -> green -> red (bad)
+vec3 heatmap(float t) {
+    t = clamp(t, 0.0, 1.0);
+    vec3 cold = vec3(0.1, 0.3, 1.0);
+    vec3 mid = vec3(0.1, 0.9, 0.2);
+    vec3 hot = vec3(1.0, 0.1, 0.05);
+    return t < 0.5 ? mix(cold, mid, t * 2.0) : mix(mid, hot, (t - 0.5) * 2.0);
+}
+
+void main() {
+    vec3 object = heatmap(values[gl_PrimitiveID]);
+
+    // Keep some shading so the shape stays readable
+    float D = clamp(dot(normalize(light), normalize(world_normal)), 0.0, 1.0);
+    object *= ambient_strength + (1.0 - ambient_strength) * D;
+
+    float d = min(coord.x, min(coord.y, coord.z));
+    d = smoothstep(u_line_thickness, u_line_thickness + u_falloff, d);
+    object = mix(object, u_wireframe_color.rgb, (1.0 - d) * u_wireframe_color.a);
+
+    color = vec4(object, 1.0);
+}
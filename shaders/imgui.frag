@@ -3,7 +3,7 @@
 layout(location = 0) in vec2 uv;
 layout(location = 1) in vec4 color;
 
-uniform sampler2D u_font;
+uniform sampler2D u_font; // @internal
 
 out vec4 out_color;
 
@@ -7,7 +7,7 @@ layout(location = 2) in vec4 color;
 layout(location = 0) out vec2 out_uv;
 layout(location = 1) out vec4 out_color;
 
-uniform mat4 u_proj;
+uniform mat4 u_proj; // @internal
 
 
 void main() {
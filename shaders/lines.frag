@@ -0,0 +1,43 @@
+#version 450 core
+
+layout(location = 0) noperspective in vec2 line_coord;
+layout(location = 1) flat in float line_length;
+layout(location = 2) flat in vec4 line_color;
+// x: pattern, y: dash length, z: gap length, w: phase, lengths in pixels
+layout(location = 3) flat in vec4 stroke;
+
+out vec4 color;
+
+uniform float u_line_width = 2.0; // @range(0.5, 16.0) @internal
+// 0: butt, 1: square, 2: round
+uniform int u_cap = 2; // @range(0, 2) @internal
+// Fade the border over a pixel, off gives hard edges
+uniform int u_smooth = 1; // @internal
+
+#include "stroke_pattern.glsl"
+
+void main() {
+    float half_width = 0.5 * u_line_width;
+    float across = abs(line_coord.y);
+
+    // Dots are zero length dashes rounded by the caps, butt caps need them to have a length
+    float dot_length = u_cap == 0 ? u_line_width : 0.0;
+    // Distance past the ends of the segment or the current dash, negative inside
+    float along = max(interval_distance(line_coord.x, 0.0, line_length), pattern_distance(line_coord.x, stroke, dot_length));
+
+    // Signed distance to the outline of the line in pixels
+    float distance;
+    if (u_cap == 2) {
+        distance = length(vec2(max(along, 0.0), across)) - half_width;
+    } else if (u_cap == 1) {
+        distance = max(across, along) - half_width;
+    } else {
+        distance = max(across - half_width, along);
+    }
+
+    float coverage = u_smooth == 1 ? clamp(0.5 - distance, 0.0, 1.0) : step(distance, 0.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+    color = vec4(line_color.rgb, line_color.a * coverage);
+}
@@ -0,0 +1,37 @@
+// Stroke patterns shared by the edge distance and the line shaders, pulled in with #include
+
+const int DASHED = 1;
+const int DOTTED = 2;
+const int DASH_DOT = 3;
+
+// Distance to the interval [a, b], negative inside
+float interval_distance(float s, float a, float b) {
+    return max(a - s, s - b);
+}
+
+// Distance along the line to the closest dash of the pattern, negative inside a dash.
+// pattern_stroke x: pattern, y: dash length, z: gap length, w: phase, lengths in pixels
+float pattern_distance(float s, vec4 pattern_stroke, float dot_length) {
+    int pattern = int(pattern_stroke.x + 0.5);
+    float dash = pattern_stroke.y;
+    float gap = pattern_stroke.z;
+    s += pattern_stroke.w;
+
+    if (pattern == DASHED) {
+        float period = max(dash + gap, 1e-3);
+        float t = mod(s, period);
+        return min(interval_distance(t, 0.0, dash), interval_distance(t, period, period + dash));
+    }
+    if (pattern == DOTTED) {
+        float period = max(dot_length + gap, 1e-3);
+        float t = mod(s, period);
+        return min(interval_distance(t, 0.0, dot_length), interval_distance(t, period, period + dot_length));
+    }
+    if (pattern == DASH_DOT) {
+        float period = max(dash + dot_length + 2.0 * gap, 1e-3);
+        float t = mod(s, period);
+        float dot_start = dash + gap;
+        return min(min(interval_distance(t, 0.0, dash), interval_distance(t, dot_start, dot_start + dot_length)), interval_distance(t, period, period + dash));
+    }
+    return -1e6;
+}
@@ -0,0 +1,35 @@
+#version 450 core
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 normal;
+layout(location = 2) in vec4 tangent;
+layout(location = 3) in vec4 bitangent;
+layout(location = 4) in vec2 uv;
+layout(location = 5) in vec2 uv1;
+layout(location = 6) in vec4 vertex_color;
+
+// Same interface as default.vert so the edge distance geometry shader can be reused
+layout(location = 0) out vec4 out_normal;
+layout(location = 1) out vec4 out_tangent;
+layout(location = 2) out vec4 out_bitangent;
+layout(location = 3) out vec2 out_uv;
+layout(location = 4) out vec3 out_world_normal;
+layout(location = 5) out vec3 out_coord;
+layout(location = 6) out vec4 out_color;
+layout(location = 7) out vec2 out_uv1;
+
+// xy: scale, zw: offset from uv space to normalized device coordinates
+uniform vec4 u_uv_transform = vec4(1.6, 1.6, -0.8, -0.8); // @internal
+
+void main() {
+    out_normal = normal;
+    out_tangent = tangent;
+    out_bitangent = bitangent;
+    out_uv = uv;
+    out_uv1 = uv1;
+    out_color = vertex_color;
+    out_world_normal = normal.xyz;
+
+    // The triangle is placed at its texture coordinates
+    gl_Position = vec4(uv * u_uv_transform.xy + u_uv_transform.zw, 0.0, 1.0);
+}
@@ -0,0 +1,26 @@
+#version 450 core
+
+layout(location = 0) in vec2 uv;
+
+out vec4 color;
+
+uniform sampler2D u_texture; // @internal
+uniform int u_use_texture = 0; // @internal
+uniform float u_checker_count = 8.0; // @range(1.0, 64.0) @internal
+uniform vec4 u_checker_color_a = vec4(0.8,0.8,0.8,1.0);
+uniform vec4 u_checker_color_b = vec4(0.6,0.6,0.6,1.0);
+uniform vec4 u_outside_color = vec4(0.2,0.2,0.2,1.0);
+
+void main() {
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+        color = u_outside_color;
+        return;
+    }
+
+    if (u_use_texture == 1) {
+        color = vec4(texture(u_texture, uv).rgb, 1.0);
+    } else {
+        ivec2 cell = ivec2(floor(uv * u_checker_count));
+        color = ((cell.x + cell.y) % 2 == 0) ? u_checker_color_a : u_checker_color_b;
+    }
+}
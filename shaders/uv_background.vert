@@ -0,0 +1,13 @@
+#version 450 core
+
+layout(location = 0) out vec2 out_uv;
+
+// xy: scale, zw: offset from uv space to normalized device coordinates
+uniform vec4 u_uv_transform = vec4(1.6, 1.6, -0.8, -0.8); // @internal
+
+// Full screen triangle generated from the vertex index, no vertex data needed
+void main() {
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    out_uv = (ndc - u_uv_transform.zw) / u_uv_transform.xy;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
@@ -0,0 +1,30 @@
+#version 450 core
+
+layout(location = 0) in vec4 normal;
+layout(location = 1) in vec4 tangent;
+layout(location = 2) in vec4 bitangent;
+layout(location = 3) in vec2 uv;
+layout(location = 4) in vec3 world_normal;
+layout(location = 5) in vec3 coord;
+
+out vec4 color;
+
+uniform float u_line_thickness = 0.02; // @range(0.001, 1.0) @internal
+uniform float u_falloff = 0.003; // @range(0.0, 0.05) @internal
+uniform vec4 u_wireframe_color = vec4(0,0,0,1.0); // @internal
+
+// Faces are filled with a translucent color, overlapping faces accumulate and stand out
+uniform vec4 u_fill_color = vec4(0.2,0.6,1.0,0.25); // @internal
+uniform vec4 u_out_of_range_color = vec4(1.0,0.3,0.0,0.5); // @internal
+
+void main() {
+    // Islands outside of the [0,1] range get highlighted
+    bool out_of_range = any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)));
+    vec4 fill = out_of_range ? u_out_of_range_color : u_fill_color;
+
+    float d = min(coord.x, min(coord.y, coord.z));
+    d = smoothstep(u_line_thickness, u_line_thickness + u_falloff, d);
+    float wire = (1.0 - d) * u_wireframe_color.a;
+
+    color = vec4(mix(fill.rgb, u_wireframe_color.rgb, wire), max(fill.a, wire));
+}
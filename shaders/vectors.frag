@@ -0,0 +1,9 @@
+#version 450 core
+
+layout(location = 0) in vec4 in_color;
+
+out vec4 color;
+
+void main() {
+    color = in_color;
+}
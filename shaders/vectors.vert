@@ -0,0 +1,18 @@
+#version 450 core
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 normal;
+layout(location = 2) in vec4 tangent;
+layout(location = 3) in vec4 bitangent;
+
+layout(location = 0) out vec4 out_normal;
+layout(location = 1) out vec4 out_tangent;
+layout(location = 2) out vec4 out_bitangent;
+
+// Vertices are transformed in the geometry shader
+void main() {
+    out_normal = normal;
+    out_tangent = tangent;
+    out_bitangent = bitangent;
+    gl_Position = position;
+}
@@ -0,0 +1,265 @@
+/// Benchmark mode
+/// ---
+/// Renders every wireframe mode on every mesh offscreen at a fixed resolution. Each run renders
+/// a number of warm-up frames followed by the measured frames. The GPU times come from the
+/// profiler, which reads its queries back a few frames later, so samples stay pending until the
+/// profiler has the frame they belong to.
+use gl::types::*;
+use std::path::{Path, PathBuf};
+
+use crate::device::stats::{self, ObjectKind};
+use crate::device::{DeviceHandle, Format, RenderTargetDesc, RenderTargetHandle};
+use crate::profiler::Profiler;
+use crate::WireframeMode;
+
+pub struct BenchmarkConfig {
+    pub meshes : Vec<String>,
+    pub modes : Vec<WireframeMode>,
+    pub warmup_frames : u32,
+    pub measured_frames : u32,
+    /// JSON file with the results, the table is always printed
+    pub output : Option<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+struct Sample {
+    cpu_ms : f32,
+    gpu_ms : f32,
+    primitives : u64,
+}
+
+/// Measured frame waiting for the profiler
+struct PendingSample {
+    run : usize,
+    profiler_frame : u64,
+    primitives_query : GLuint,
+}
+
+/// Statistics of one mesh and mode
+pub struct RunResult {
+    pub mesh : String,
+    pub mode : WireframeMode,
+    pub frames : usize,
+    pub cpu_mean_ms : f32,
+    pub cpu_p95_ms : f32,
+    pub gpu_mean_ms : f32,
+    pub gpu_p95_ms : f32,
+    pub primitives : u64,
+}
+
+pub struct Benchmark {
+    config : BenchmarkConfig,
+    /// Offscreen target all runs render into
+    pub target : RenderTargetHandle,
+    run : usize,
+    frame : u32,
+    samples : Vec<Vec<Sample>>,
+    pending : Vec<PendingSample>,
+    active_query : Option<GLuint>,
+    skipped : Vec<bool>,
+}
+
+impl Benchmark {
+    pub fn new(config : BenchmarkConfig, device : &DeviceHandle, size : (u32, u32), samples : u32) -> Result<Benchmark, String> {
+        let target = device.borrow().create_render_target(&RenderTargetDesc {
+            sample_count: samples.max(1).min(device.borrow().max_sample_count()),
+            ..RenderTargetDesc::color_depth(size.0, size.1, Format::Rgba8, Format::Depth24Stencil8).with_label("benchmark")
+        })?;
+
+        let runs = config.meshes.len() * config.modes.len();
+        Ok(Benchmark {
+            config,
+            target,
+            run: 0,
+            frame: 0,
+            samples: vec![Vec::new(); runs],
+            pending: Vec::new(),
+            active_query: None,
+            skipped: vec![false; runs],
+        })
+    }
+
+    fn run_count(&self) -> usize {
+        self.config.meshes.len() * self.config.modes.len()
+    }
+
+    /// Mesh and mode of the current run, `None` once all runs are done
+    pub fn current(&self) -> Option<(String, WireframeMode)> {
+        if self.run >= self.run_count() {
+            return None;
+        }
+        let modes = self.config.modes.len();
+        Some((self.config.meshes[self.run / modes].clone(), self.config.modes[self.run % modes]))
+    }
+
+    pub fn finished(&self) -> bool {
+        self.run >= self.run_count()
+    }
+
+    /// Skips the remaining runs of the current mesh, used when it fails to load
+    pub fn skip_mesh(&mut self) {
+        let modes = self.config.modes.len();
+        let next = (self.run / modes + 1) * modes;
+        for run in self.run..next.min(self.run_count()) {
+            self.skipped[run] = true;
+        }
+        self.run = next;
+        self.frame = 0;
+    }
+
+    fn measuring(&self) -> bool {
+        !self.finished() && self.frame >= self.config.warmup_frames
+    }
+
+    /// Call before rendering the frame
+    pub fn begin_frame(&mut self) {
+        if !self.measuring() {
+            return;
+        }
+        let mut query = 0;
+        unsafe {
+            gl::GenQueries(1, &mut query);
+            gl::BeginQuery(gl::PRIMITIVES_GENERATED, query);
+        }
+        stats::object_created(ObjectKind::Query, 1);
+        self.active_query = Some(query);
+    }
+
+    /// Call after the profiler finished the frame
+    pub fn end_frame(&mut self, profiler : &Profiler) {
+        if let Some(query) = self.active_query.take() {
+            unsafe {
+                gl::EndQuery(gl::PRIMITIVES_GENERATED);
+            }
+            self.pending.push(PendingSample {
+                run: self.run,
+                profiler_frame: profiler.last_frame_index(),
+                primitives_query: query,
+            });
+        }
+
+        if !self.finished() {
+            self.frame += 1;
+            if self.frame >= self.config.warmup_frames + self.config.measured_frames {
+                self.run += 1;
+                self.frame = 0;
+            }
+        }
+
+        self.resolve(profiler);
+    }
+
+    /// Turns the pending samples the profiler has results for into samples
+    fn resolve(&mut self, profiler : &Profiler) {
+        let samples = &mut self.samples;
+        self.pending.retain(|pending| {
+            let frame = match profiler.history().iter().find(|f| f.index == pending.profiler_frame) {
+                Some(frame) => frame,
+                None => return true,
+            };
+
+            let mut primitives : GLuint64 = 0;
+            unsafe {
+                gl::GetQueryObjectui64v(pending.primitives_query, gl::QUERY_RESULT, &mut primitives);
+                gl::DeleteQueries(1, &pending.primitives_query);
+            }
+            stats::object_deleted(ObjectKind::Query, 1);
+            samples[pending.run].push(Sample {
+                cpu_ms: frame.cpu_frame_ms,
+                gpu_ms: frame.gpu_total_ms(),
+                primitives,
+            });
+            false
+        });
+    }
+
+    /// Waits for the last results and computes the statistics of every run
+    pub fn finish(mut self, profiler : &mut Profiler) -> Vec<RunResult> {
+        profiler.flush();
+        self.resolve(profiler);
+        for pending in self.pending.iter() {
+            unsafe {
+                gl::DeleteQueries(1, &pending.primitives_query);
+            }
+            stats::object_deleted(ObjectKind::Query, 1);
+        }
+
+        let modes = self.config.modes.len();
+        let mut results = Vec::new();
+        for (run, samples) in self.samples.iter().enumerate() {
+            if self.skipped[run] || samples.is_empty() {
+                continue;
+            }
+            let cpu : Vec<f32> = samples.iter().map(|s| s.cpu_ms).collect();
+            let gpu : Vec<f32> = samples.iter().map(|s| s.gpu_ms).collect();
+            results.push(RunResult {
+                mesh: self.config.meshes[run / modes].clone(),
+                mode: self.config.modes[run % modes],
+                frames: samples.len(),
+                cpu_mean_ms: mean(&cpu),
+                cpu_p95_ms: percentile(&cpu, 0.95),
+                gpu_mean_ms: mean(&gpu),
+                gpu_p95_ms: percentile(&gpu, 0.95),
+                primitives: samples.iter().map(|s| s.primitives).sum::<u64>() / samples.len() as u64,
+            });
+        }
+        results
+    }
+
+    pub fn output(&self) -> Option<&Path> {
+        self.config.output.as_ref().map(|p| p.as_path())
+    }
+}
+
+fn mean(values : &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+/// Nearest rank percentile
+fn percentile(values : &[f32], p : f32) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let rank = ((p * sorted.len() as f32).ceil() as usize).max(1).min(sorted.len());
+    sorted.get(rank - 1).cloned().unwrap_or(0.0)
+}
+
+/// Formats the results as a plain text table
+pub fn table(results : &[RunResult]) -> String {
+    let mut table = format!(
+        "{:<32} {:<24} {:>10} {:>10} {:>10} {:>10} {:>12}\n",
+        "Mesh", "Mode", "CPU mean", "CPU p95", "GPU mean", "GPU p95", "Primitives"
+    );
+    for r in results {
+        table.push_str(&format!(
+            "{:<32} {:<24} {:>8.3}ms {:>8.3}ms {:>8.3}ms {:>8.3}ms {:>12}\n",
+            r.mesh, r.mode.name(), r.cpu_mean_ms, r.cpu_p95_ms, r.gpu_mean_ms, r.gpu_p95_ms, r.primitives
+        ));
+    }
+    table
+}
+
+/// Escapes a string for a JSON document
+fn json_string(value : &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Writes the results as a JSON array, one object per run
+pub fn write_json(path : &Path, results : &[RunResult], size : (u32, u32)) -> Result<(), String> {
+    let runs : Vec<String> = results.iter().map(|r| format!(
+        "  {{\"mesh\": {}, \"mode\": {}, \"width\": {}, \"height\": {}, \"frames\": {}, \"cpu_mean_ms\": {}, \"cpu_p95_ms\": {}, \"gpu_mean_ms\": {}, \"gpu_p95_ms\": {}, \"primitives\": {}}}",
+        json_string(&r.mesh), json_string(r.mode.name()), size.0, size.1, r.frames,
+        r.cpu_mean_ms, r.cpu_p95_ms, r.gpu_mean_ms, r.gpu_p95_ms, r.primitives
+    )).collect();
+    let json = format!("[\n{}\n]\n", runs.join(",\n"));
+    std::fs::write(path, json).map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))
+}
@@ -0,0 +1,200 @@
+/// Reading back rendered images and writing them to disk
+use gl::types::*;
+use std::path::Path;
+
+use crate::device::{DeviceHandle, Format, Rect, RenderPassDesc, RenderTargetDesc, RenderTargetHandle};
+
+/// Reads back the color buffer of the currently bound read framebuffer as tightly packed RGBA8.
+/// Rows are flipped so the first row is the top of the image.
+pub fn read_pixels(x : i32, y : i32, width : u32, height : u32) -> Vec<u8> {
+    let row_size = width as usize * 4;
+    let mut pixels = vec![0u8; row_size * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            x,
+            y,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut std::ffi::c_void,
+        );
+    }
+
+    flip_rows(&mut pixels, row_size);
+    pixels
+}
+
+/// OpenGL returns images bottom-up, flip them to top-down
+fn flip_rows(pixels : &mut [u8], row_size : usize) {
+    let rows = pixels.len() / row_size;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - row - 1) * row_size);
+        top[row * row_size..(row + 1) * row_size].swap_with_slice(&mut bottom[..row_size]);
+    }
+}
+
+/// Writes tightly packed RGBA8 pixels to a PNG file
+pub fn write_png(path : &Path, width : u32, height : u32, pixels : &[u8]) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create \"{}\": {}", path.display(), e))?;
+
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()
+        .map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))?;
+    writer.write_image_data(pixels)
+        .map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))
+}
+
+/// Saves the default framebuffer to a PNG file
+pub fn save_framebuffer(path : &Path, width : u32, height : u32) -> Result<(), String> {
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl::ReadBuffer(gl::BACK);
+    }
+    let pixels = read_pixels(0, 0, width, height);
+    write_png(path, width, height, &pixels)
+}
+
+/// Largest tile we render at once, bigger captures are split up
+const MAX_TILE_SIZE : u32 = 4096;
+
+/// Largest tile the driver lets us render into
+pub fn max_tile_size() -> u32 {
+    let mut texture_size = 0;
+    let mut renderbuffer_size = 0;
+    let mut viewport = [0; 2];
+    unsafe {
+        gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut texture_size);
+        gl::GetIntegerv(gl::MAX_RENDERBUFFER_SIZE, &mut renderbuffer_size);
+        gl::GetIntegerv(gl::MAX_VIEWPORT_DIMS, viewport.as_mut_ptr());
+    }
+    [texture_size, renderbuffer_size, viewport[0], viewport[1]]
+        .iter()
+        .filter(|s| **s > 0)
+        .fold(MAX_TILE_SIZE, |size, s| size.min(*s as u32))
+}
+
+/// Part of a capture image, in pixels with the origin at the bottom left like OpenGL
+#[derive(Clone, Copy)]
+pub struct Tile {
+    pub x : u32,
+    pub y : u32,
+    pub width : u32,
+    pub height : u32,
+    pub image_width : u32,
+    pub image_height : u32,
+}
+
+impl Tile {
+    /// Scale (xy) and offset (zw) that map normalized device coordinates of the full image
+    /// to the ones of this tile
+    pub fn ndc_transform(&self) -> [f32; 4] {
+        let sx = self.image_width as f32 / self.width as f32;
+        let sy = self.image_height as f32 / self.height as f32;
+        [
+            sx,
+            sy,
+            sx - 1.0 - 2.0 * self.x as f32 / self.width as f32,
+            sy - 1.0 - 2.0 * self.y as f32 / self.height as f32,
+        ]
+    }
+}
+
+/// Splits an image in equally sized tiles no larger than `max_size`.
+/// The tiles on the right and top may extend past the image.
+pub fn tiles(width : u32, height : u32, max_size : u32) -> Vec<Tile> {
+    let count = ((width.max(height) + max_size - 1) / max_size).max(1);
+    let tile_width = (width + count - 1) / count;
+    let tile_height = (height + count - 1) / count;
+
+    let mut tiles = Vec::new();
+    for ty in 0..count {
+        for tx in 0..count {
+            if tx * tile_width >= width || ty * tile_height >= height {
+                continue;
+            }
+            tiles.push(Tile {
+                x: tx * tile_width,
+                y: ty * tile_height,
+                width: tile_width,
+                height: tile_height,
+                image_width: width,
+                image_height: height,
+            });
+        }
+    }
+    tiles
+}
+
+/// Renders an image of `width` x `height` offscreen, `render` is called once per tile inside a
+/// render pass on the offscreen target it gets passed. With more than one sample the tiles are rendered
+/// multisampled and resolved before they are read back. Returns top-down RGBA8 pixels.
+pub fn render_tiled(device : &DeviceHandle, width : u32, height : u32, samples : u32, render : &mut dyn FnMut(&Tile, &RenderTargetHandle)) -> Result<Vec<u8>, String> {
+    let tiles = tiles(width, height, max_tile_size());
+    let (tile_width, tile_height) = (tiles[0].width, tiles[0].height);
+    let target = device.borrow().create_render_target(&RenderTargetDesc::color_depth(tile_width, tile_height, Format::Rgba8, Format::Depth24Stencil8).with_label("capture tile"))?;
+    let msaa_target = if samples > 1 {
+        Some(device.borrow().create_render_target(&RenderTargetDesc {
+            sample_count: samples.min(device.borrow().max_sample_count()),
+            ..RenderTargetDesc::color_depth(tile_width, tile_height, Format::Rgba8, Format::Depth24Stencil8).with_label("capture msaa tile")
+        })?)
+    } else {
+        None
+    };
+
+    let mut begin_pass = device.borrow().create_command_list();
+    begin_pass.begin_render_pass(Some(msaa_target.as_ref().unwrap_or(&target)), &RenderPassDesc::clear([0.0, 0.0, 0.0, 0.0]));
+    let mut end_pass = device.borrow().create_command_list();
+    end_pass.end_render_pass();
+    if let Some(msaa_target) = &msaa_target {
+        end_pass.resolve(msaa_target, Some(&target), Rect::new(0, 0, tile_width, tile_height));
+    }
+
+    let row_size = width as usize * 4;
+    let mut image = vec![0u8; row_size * height as usize];
+    for tile in tiles.iter() {
+        begin_pass.execute(device);
+        render(tile, msaa_target.as_ref().unwrap_or(&target));
+        end_pass.execute(device);
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target.native_handle());
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+        }
+        let pixels = read_pixels(0, 0, tile.width, tile.height);
+
+        // Copy the rows that fall inside the image, both are stored top-down
+        let copy_width = (width - tile.x).min(tile.width) as usize * 4;
+        for row in 0..tile.height {
+            let image_y = tile.y + (tile.height - 1 - row);
+            if image_y >= height {
+                continue;
+            }
+            let src = row as usize * tile.width as usize * 4;
+            let dst = (height - 1 - image_y) as usize * row_size + tile.x as usize * 4;
+            image[dst..dst + copy_width].copy_from_slice(&pixels[src..src + copy_width]);
+        }
+    }
+
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+    Ok(image)
+}
+
+/// File name for a new screenshot in `directory`, based on the current time
+pub fn screenshot_path(directory : &Path) -> std::path::PathBuf {
+    let stamp = time::strftime("%Y%m%d_%H%M%S", &time::now()).unwrap_or_default();
+    let mut path = directory.join(format!("wireframe_{}.png", stamp));
+    let mut index = 1;
+    while path.exists() {
+        path = directory.join(format!("wireframe_{}_{}.png", stamp, index));
+        index += 1;
+    }
+    path
+}
@@ -0,0 +1,264 @@
+/// Command-line interface
+/// ---
+/// Small hand written argument parser, the options override the values loaded from the config file.
+use std::path::PathBuf;
+
+use crate::settings::{Settings, DEFAULT_CONFIG_PATH};
+use crate::WireframeMode;
+
+pub const USAGE : &str = "Usage: wireframe [OPTIONS] [MESH]...
+       wireframe bake <DIR> [--config <path>]
+
+Arguments:
+  [MESH]...                  Mesh file(s) to open, the first one is shown on startup
+
+Commands:
+  bake <DIR>                 Write mesh caches for every supported file in DIR using the
+                             processing options of the config file, without opening a window
+
+Options:
+  --config <path>            Render settings file (default: wireframe.toml)
+  --mode <mode>              Initial wireframe mode (see --list-modes)
+  --line-thickness <value>   Line thickness
+  --solid-color <color>      Solid color as \"r,g,b[,a]\" (0-1) or \"#rrggbb[aa]\"
+  --line-color <color>       Wireframe color as \"r,g,b[,a]\" (0-1) or \"#rrggbb[aa]\"
+  --size <width>x<height>    Window size
+  --vsync / --no-vsync       Enable or disable vsync
+  --output <path>            Render headless and write the final frame to a PNG
+  --scale <n>                Render --output at n times the window size (default: 1)
+  --turntable <path>         Render headless and record a turntable of the mesh in the --mode. A .gif or
+                             .apng extension writes an animation, anything else a numbered PNG sequence
+  --turntable-frames <count> Frames for a full revolution of the turntable (default: 72)
+  --export <path>            Render headless and export the wireframe line art as SVG or PDF (by extension)
+  --frames <count>           Number of frames to render before writing --output/--export (default: 1)
+  --benchmark                Render every wireframe mode on every [MESH] offscreen at the window size with
+                             vsync off and print the mean/p95 CPU and GPU frame times and primitives drawn
+  --benchmark-output <path>  Also write the --benchmark results as JSON
+  --benchmark-frames <count> Measured frames per mesh and mode (default: 200)
+  --benchmark-warmup <count> Frames rendered before measuring each mesh and mode (default: 50)
+  --list-modes               Print the available wireframe modes and exit
+  -h, --help                 Print this help and exit";
+
+/// Parsed command-line options. Options that were not passed are `None`.
+#[derive(Default)]
+pub struct Options {
+    pub config : Option<PathBuf>,
+    /// Directory to bake mesh caches for
+    pub bake : Option<PathBuf>,
+    pub meshes : Vec<String>,
+    pub mode : Option<WireframeMode>,
+    pub line_thickness : Option<f32>,
+    pub solid_color : Option<[f32; 4]>,
+    pub line_color : Option<[f32; 4]>,
+    pub size : Option<(u32, u32)>,
+    pub vsync : Option<bool>,
+    pub output : Option<PathBuf>,
+    pub export : Option<PathBuf>,
+    pub scale : u32,
+    pub turntable : Option<PathBuf>,
+    pub turntable_frames : Option<u32>,
+    pub frames : u32,
+    pub benchmark : bool,
+    pub benchmark_output : Option<PathBuf>,
+    pub benchmark_frames : u32,
+    pub benchmark_warmup : u32,
+    pub list_modes : bool,
+    pub help : bool,
+}
+
+impl Options {
+    pub fn from_env() -> Result<Options, String> {
+        Options::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I : Iterator<Item = String>>(args : I) -> Result<Options, String> {
+        let mut options = Options {
+            frames: 1,
+            scale: 1,
+            benchmark_frames: 200,
+            benchmark_warmup: 50,
+            ..Default::default()
+        };
+
+        let mut args = args.peekable();
+        if args.peek().map(|a| a == "bake").unwrap_or(false) {
+            args.next();
+            options.bake = Some(PathBuf::from(args.next().ok_or("Missing directory for \"bake\"")?));
+        }
+
+        while let Some(arg) = args.next() {
+            let mut value = |name : &str| args.next().ok_or(format!("Missing value for \"{}\"", name));
+            match arg.as_str() {
+                "--config" => options.config = Some(PathBuf::from(value(&arg)?)),
+                "--mode" => {
+                    let v = value(&arg)?;
+                    options.mode = Some(WireframeMode::from_name(&v).ok_or(format!("Unknown wireframe mode \"{}\", see --list-modes", v))?);
+                },
+                "--line-thickness" => options.line_thickness = Some(parse_number(&value(&arg)?)?),
+                "--solid-color" => options.solid_color = Some(parse_color(&value(&arg)?)?),
+                "--line-color" => options.line_color = Some(parse_color(&value(&arg)?)?),
+                "--size" => options.size = Some(parse_size(&value(&arg)?)?),
+                "--vsync" => options.vsync = Some(true),
+                "--no-vsync" => options.vsync = Some(false),
+                "--output" => options.output = Some(PathBuf::from(value(&arg)?)),
+                "--scale" => {
+                    let v = value(&arg)?;
+                    options.scale = v.parse::<u32>().map_err(|_| format!("Invalid scale \"{}\"", v))?.max(1);
+                },
+                "--turntable" => options.turntable = Some(PathBuf::from(value(&arg)?)),
+                "--turntable-frames" => {
+                    let v = value(&arg)?;
+                    options.turntable_frames = Some(v.parse::<u32>().map_err(|_| format!("Invalid frame count \"{}\"", v))?.max(1));
+                },
+                "--export" => options.export = Some(PathBuf::from(value(&arg)?)),
+                "--frames" => {
+                    let v = value(&arg)?;
+                    options.frames = v.parse::<u32>().map_err(|_| format!("Invalid frame count \"{}\"", v))?.max(1);
+                },
+                "--benchmark" => options.benchmark = true,
+                "--benchmark-output" => options.benchmark_output = Some(PathBuf::from(value(&arg)?)),
+                "--benchmark-frames" => {
+                    let v = value(&arg)?;
+                    options.benchmark_frames = v.parse::<u32>().map_err(|_| format!("Invalid frame count \"{}\"", v))?.max(1);
+                },
+                "--benchmark-warmup" => {
+                    let v = value(&arg)?;
+                    options.benchmark_warmup = v.parse::<u32>().map_err(|_| format!("Invalid frame count \"{}\"", v))?;
+                },
+                "--list-modes" => options.list_modes = true,
+                "-h" | "--help" => options.help = true,
+                _ if arg.starts_with('-') => return Err(format!("Unknown option \"{}\"", arg)),
+                _ if options.bake.is_some() => return Err(format!("Unexpected argument \"{}\" for \"bake\"", arg)),
+                _ => options.meshes.push(arg),
+            }
+        }
+
+        Ok(options)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+    }
+
+    /// Whether the application renders without showing a window
+    pub fn headless(&self) -> bool {
+        self.output.is_some() || self.export.is_some() || self.turntable.is_some() || self.benchmark
+    }
+
+    /// Meshes to benchmark, the mesh of the config file when none were passed
+    pub fn benchmark_meshes(&self, settings : &Settings) -> Vec<String> {
+        if self.meshes.is_empty() {
+            vec![settings.mesh.clone()]
+        } else {
+            self.meshes.clone()
+        }
+    }
+
+    /// Overrides the loaded settings with the options passed on the command line
+    pub fn apply(&self, settings : &mut Settings) {
+        if let Some(mesh) = self.meshes.first() {
+            settings.mesh = mesh.clone();
+        }
+        if let Some(mode) = self.mode {
+            settings.render.mode = mode;
+        }
+        if let Some(thickness) = self.line_thickness {
+            settings.render.line_thickness = thickness;
+        }
+        if let Some(color) = self.solid_color {
+            settings.render.solid_color = color;
+        }
+        if let Some(color) = self.line_color {
+            settings.render.line_color = color;
+        }
+        if let Some((width, height)) = self.size {
+            settings.window.width = width;
+            settings.window.height = height;
+        }
+        if let Some(vsync) = self.vsync {
+            settings.window.vsync = vsync;
+        }
+        if self.benchmark {
+            // Frame times are meaningless when waiting for the display
+            settings.window.vsync = false;
+        }
+        if let Some(frames) = self.turntable_frames {
+            settings.turntable.frames = frames;
+        }
+    }
+}
+
+/// Prints the names accepted by `--mode`
+pub fn print_modes() {
+    for mode in WireframeMode::ALL.iter() {
+        println!("{:<24} {}", mode.name(), mode.description());
+    }
+}
+
+fn parse_number(value : &str) -> Result<f32, String> {
+    value.parse::<f32>().map_err(|_| format!("Invalid number \"{}\"", value))
+}
+
+/// Parses `<width>x<height>`
+fn parse_size(value : &str) -> Result<(u32, u32), String> {
+    let error = || format!("Invalid size \"{}\", expected <width>x<height>", value);
+    let mut parts = value.split('x');
+    let width = parts.next().and_then(|w| w.parse::<u32>().ok()).ok_or_else(error)?;
+    let height = parts.next().and_then(|h| h.parse::<u32>().ok()).ok_or_else(error)?;
+    if parts.next().is_some() || width == 0 || height == 0 {
+        return Err(error());
+    }
+    Ok((width, height))
+}
+
+/// Parses a color from `r,g,b[,a]` floats or a `#rrggbb[aa]` hex string
+fn parse_color(value : &str) -> Result<[f32; 4], String> {
+    let error = || format!("Invalid color \"{}\"", value);
+    let mut color = [0.0, 0.0, 0.0, 1.0];
+
+    if value.starts_with('#') {
+        let hex = &value[1..];
+        // Checked first so the byte ranges below never split a character
+        if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        for i in 0..hex.len() / 2 {
+            let byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+            color[i] = byte as f32 / 255.0;
+        }
+        return Ok(color);
+    }
+
+    let components : Vec<&str> = value.split(',').collect();
+    if components.len() != 3 && components.len() != 4 {
+        return Err(error());
+    }
+    for (i, c) in components.iter().enumerate() {
+        color[i] = c.trim().parse::<f32>().map_err(|_| error())?;
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_color;
+
+    #[test]
+    fn parse_hex_colors() {
+        assert_eq!(parse_color("#ff0000"), Ok([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_color("#00FF0080"), Ok([0.0, 1.0, 0.0, 128.0 / 255.0]));
+    }
+
+    #[test]
+    fn parse_component_colors() {
+        assert_eq!(parse_color("0.1,0.2,0.3"), Ok([0.1, 0.2, 0.3, 1.0]));
+        assert_eq!(parse_color("0.1, 0.2, 0.3, 0.5"), Ok([0.1, 0.2, 0.3, 0.5]));
+    }
+
+    #[test]
+    fn reject_invalid_colors() {
+        for value in &["", "#", "#fff", "#ff00000", "#gg0000", "#+f0000", "#ff00é0", "#fé00000", "1,2", "1,2,3,4,5", "1,x,3"] {
+            assert!(parse_color(value).is_err(), "\"{}\" should be rejected", value);
+        }
+    }
+}
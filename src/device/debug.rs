@@ -0,0 +1,190 @@
+/// Debug output of the graphics API
+/// ---
+/// The device decodes the messages of the driver and pushes them into a `DebugLog`. The log
+/// filters them by severity and id, folds repeated messages into one entry with a count and keeps
+/// the most recent ones for the console. The driver may call back from another thread unless
+/// the output is synchronous, so the log is shared behind a mutex.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub const ALL : [Severity; 4] = [Severity::Notification, Severity::Low, Severity::Medium, Severity::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Notification => "notification",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+
+    pub fn to_int(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_int(value : u32) -> Severity {
+        *Severity::ALL.get(value as usize).unwrap_or(&Severity::High)
+    }
+}
+
+#[derive(Clone)]
+pub struct DebugMessage {
+    /// Decoded names of the API enums
+    pub source : &'static str,
+    pub kind : &'static str,
+    pub severity : Severity,
+    pub id : u32,
+    pub text : String,
+    /// API errors, as opposed to performance or portability warnings
+    pub is_error : bool,
+    /// Times the message was received, repeats are folded when de-duplicating
+    pub count : u32,
+}
+
+impl DebugMessage {
+    fn same_as(&self, other : &DebugMessage) -> bool {
+        self.id == other.id && self.source == other.source && self.kind == other.kind && self.text == other.text
+    }
+}
+
+impl std::fmt::Display for DebugMessage {
+    fn fmt(&self, f : &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{} {} {}, id {}] {}", self.severity.name(), self.source, self.kind, self.id, self.text)
+    }
+}
+
+/// Which messages the log keeps
+#[derive(Clone)]
+pub struct DebugFilter {
+    pub min_severity : Severity,
+    pub ignored_ids : Vec<u32>,
+    pub deduplicate : bool,
+}
+
+impl Default for DebugFilter {
+    fn default() -> Self {
+        DebugFilter {
+            min_severity: Severity::Low,
+            ignored_ids: Vec::new(),
+            deduplicate: true,
+        }
+    }
+}
+
+pub struct DebugLog {
+    pub filter : DebugFilter,
+    /// Print the messages that pass the filter to stdout as well, for runs without the console
+    pub echo : bool,
+    /// Calls `debug_break` for errors, only useful with synchronous output
+    pub break_on_error : bool,
+    /// Number of messages kept for the console
+    pub capacity : usize,
+    messages : VecDeque<DebugMessage>,
+    /// Messages dropped by the filter
+    filtered : u64,
+}
+
+pub type DebugLogHandle = Arc<Mutex<DebugLog>>;
+
+impl DebugLog {
+    pub fn new(capacity : usize) -> DebugLog {
+        DebugLog {
+            filter: DebugFilter::default(),
+            echo: false,
+            break_on_error: false,
+            capacity,
+            messages: VecDeque::new(),
+            filtered: 0,
+        }
+    }
+
+    pub fn shared(capacity : usize) -> DebugLogHandle {
+        Arc::new(Mutex::new(DebugLog::new(capacity)))
+    }
+
+    /// Messages kept in the log, oldest first
+    pub fn messages(&self) -> &VecDeque<DebugMessage> {
+        &self.messages
+    }
+
+    pub fn filtered(&self) -> u64 {
+        self.filtered
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.filtered = 0;
+    }
+
+    pub fn push(&mut self, message : DebugMessage) {
+        if message.severity < self.filter.min_severity || self.filter.ignored_ids.contains(&message.id) {
+            self.filtered += 1;
+            return;
+        }
+
+        if message.is_error && self.break_on_error {
+            debug_break(&message);
+        }
+
+        // Repeats move to the end so the console shows when they happened last
+        if self.filter.deduplicate {
+            if let Some(index) = self.messages.iter().position(|m| m.same_as(&message)) {
+                let mut existing = self.messages.remove(index).unwrap();
+                existing.count += message.count;
+                self.messages.push_back(existing);
+                return;
+            }
+        }
+
+        if self.echo {
+            println!("{}", message);
+        }
+        self.messages.push_back(message);
+        while self.messages.len() > self.capacity {
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// Called for errors when breaking on them. With synchronous output this runs inside the API call
+/// that caused the error, so the debugger stops with the call stack of the error. Without a
+/// debugger attached the trap ends the process.
+#[inline(never)]
+pub fn debug_break(message : &DebugMessage) {
+    eprintln!("Break on error: {}", message);
+    unsafe {
+        trap();
+    }
+}
+
+#[cfg(unix)]
+unsafe fn trap() {
+    extern "C" {
+        fn raise(signal : i32) -> i32;
+    }
+    // Same number on Linux and macOS
+    const SIGTRAP : i32 = 5;
+    raise(SIGTRAP);
+}
+
+#[cfg(windows)]
+unsafe fn trap() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DebugBreak();
+    }
+    DebugBreak();
+}
+
+#[cfg(not(any(unix, windows)))]
+unsafe fn trap() {}
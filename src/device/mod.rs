@@ -1,4 +1,6 @@
 pub mod opengl;
+pub mod stats;
+pub mod debug;
 use std::cell::RefCell;
 use std::sync::Arc;
 use crate::pipeline::Pipeline;
@@ -10,6 +12,7 @@ use crate::helpers;
 pub enum PrimitiveTopology {
     Triangles,
     Lines,
+    Points,
 }
 
 impl PrimitiveTopology {
@@ -17,6 +20,7 @@ impl PrimitiveTopology {
         match top {
             PrimitiveTopology::Triangles => gl::TRIANGLES,
             PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::Points => gl::POINTS,
         }
     }
 }
@@ -34,6 +38,7 @@ impl Deref for DeviceHandle {
 }
 
 type BufferHandle = gl::types::GLuint;
+pub type RenderTargetHandle = Arc<dyn RenderTarget>;
 
 type PipelineHandle = Arc<Pipeline>;
 // type BufferHandle = Arc<Buffer>;
@@ -42,10 +47,157 @@ pub enum IndexType {
     UnsignedShort,
     UnsignedInt,
 }
+
+/// Texel formats of render target attachments
+#[allow(dead_code)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+    Rgba8,
+    Rgba16F,
+    Rgba32F,
+    R32F,
+    Depth24Stencil8,
+    Depth32F,
+}
+
+impl Format {
+    pub fn is_depth(self) -> bool {
+        match self {
+            Format::Depth24Stencil8 | Format::Depth32F => true,
+            _ => false,
+        }
+    }
+}
+
+/// Description of the attachments of a render target
+#[derive(Clone)]
+pub struct RenderTargetDesc {
+    pub width : u32,
+    pub height : u32,
+    pub color_formats : Vec<Format>,
+    pub depth_format : Option<Format>,
+    /// Number of samples per pixel, 1 disables multisampling
+    pub sample_count : u32,
+    /// Name of the target in the debug output and frame capture tools
+    pub label : String,
+}
+
+impl RenderTargetDesc {
+    /// Single color attachment with a depth buffer, the common case
+    pub fn color_depth(width : u32, height : u32, color : Format, depth : Format) -> RenderTargetDesc {
+        RenderTargetDesc {
+            width,
+            height,
+            color_formats: vec![color],
+            depth_format: Some(depth),
+            sample_count: 1,
+            label: String::new(),
+        }
+    }
+
+    pub fn with_label(self, label : &str) -> RenderTargetDesc {
+        RenderTargetDesc {
+            label: label.to_string(),
+            ..self
+        }
+    }
+}
+
+/// Offscreen color and depth attachments that can be rendered to
+pub trait RenderTarget {
+    fn desc(&self) -> &RenderTargetDesc;
+
+    /// API object of the target, the framebuffer name for OpenGL
+    fn native_handle(&self) -> u32;
+
+    /// Texture of a color attachment, used to sample the result in a later pass
+    fn color_texture(&self, index : usize) -> Option<u32>;
+}
+
+/// What happens to an attachment at the start of a render pass
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum LoadOp<T> {
+    /// Keep the previous contents
+    Load,
+    Clear(T),
+    /// The previous contents are not needed
+    DontCare,
+}
+
+/// What happens to an attachment at the end of a render pass
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum StoreOp {
+    Store,
+    /// The contents are not needed after the pass
+    DontCare,
+}
+
+#[derive(Copy, Clone)]
+pub struct RenderPassDesc {
+    pub color_load : LoadOp<[f32; 4]>,
+    pub color_store : StoreOp,
+    pub depth_load : LoadOp<f32>,
+    pub depth_store : StoreOp,
+}
+
+impl RenderPassDesc {
+    /// Clears color and depth and keeps the color result
+    pub fn clear(color : [f32; 4]) -> RenderPassDesc {
+        RenderPassDesc {
+            color_load: LoadOp::Clear(color),
+            color_store: StoreOp::Store,
+            depth_load: LoadOp::Clear(1.0),
+            depth_store: StoreOp::DontCare,
+        }
+    }
+
+    /// Continues rendering on top of the current contents
+    pub fn load() -> RenderPassDesc {
+        RenderPassDesc {
+            color_load: LoadOp::Load,
+            color_store: StoreOp::Store,
+            depth_load: LoadOp::Load,
+            depth_store: StoreOp::Store,
+        }
+    }
+}
+
+/// Rectangle in pixels with the origin at the bottom left
+#[derive(Copy, Clone)]
+pub struct Rect {
+    pub x : i32,
+    pub y : i32,
+    pub width : u32,
+    pub height : u32,
+}
+
+impl Rect {
+    pub fn new(x : i32, y : i32, width : u32, height : u32) -> Rect {
+        Rect { x, y, width, height }
+    }
+}
+
 pub trait CommandList {
+    /// Runs the commands, inside a debug group named after the list when it has a name
     fn execute(&self, device : &DeviceHandle);
+    /// Name of the list in the statistics, the debug output and frame capture tools
+    fn set_name(&mut self, name : &str);
     fn clear(&mut self, clear_color : [f32; 4], depth : Option<f32>);
 
+    /// Starts rendering into `target`, `None` is the window. The viewport covers the target.
+    fn begin_render_pass(&mut self, target : Option<&RenderTargetHandle>, desc : &RenderPassDesc);
+    fn end_render_pass(&mut self);
+    fn set_viewport(&mut self, rect : Rect);
+    /// Restricts rendering to `rect`, `None` disables the scissor test
+    fn set_scissor(&mut self, rect : Option<Rect>);
+    /// Groups the following commands under `name` in frame capture tools, groups can be nested
+    fn push_debug_group(&mut self, name : &str);
+    fn pop_debug_group(&mut self);
+    /// Resolves (or copies) the first color attachment of `source` into `destination`, `None` is the window
+    fn resolve(&mut self, source : &RenderTargetHandle, destination : Option<&RenderTargetHandle>, rect : Rect);
+
     fn draw(&mut self, vertex_count : u32, instance_count : u32, first_vertex : u32, first_instance : u32);
     fn draw_indexed(&mut self, index_count : u32, instance_count : u32, first_index : u32, vertex_offset : u32, first_instance : u32);
 
@@ -59,9 +211,17 @@ pub trait Device {
     // eg: OpenGL has Rc as it does not support any multithreading anyways
     fn supports_multithreading(&self) -> bool;
 
-    fn enable_debug_layer(&self) -> Result< (), String>;
+    /// Routes the debug output of the API into `log`. Synchronous output reports messages inside the
+    /// call that caused them, which is slower but gives a useful call stack.
+    fn enable_debug_layer(&mut self, log : &debug::DebugLogHandle, synchronous : bool) -> Result< (), String>;
+    fn set_debug_synchronous(&self, synchronous : bool);
 
     fn create_command_list(&self) -> Box<dyn CommandList>;
+
+    fn create_render_target(&self, desc : &RenderTargetDesc) -> Result<RenderTargetHandle, String>;
+
+    /// Highest sample count render targets can be created with
+    fn max_sample_count(&self) -> u32;
 }
 
 pub fn create_default_device(window : &sdl2::video::Window) -> DeviceHandle {
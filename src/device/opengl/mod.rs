@@ -5,6 +5,9 @@ use gl::types::*;
 
 use crate::pipeline::Pipeline;
 use crate::device::PrimitiveTopology;
+use crate::device::stats::{self, ObjectKind};
+use crate::device::debug::{DebugLog, DebugLogHandle, DebugMessage, Severity};
+use std::sync::Mutex;
 
 type GLError = GLint;
 #[allow(dead_code)]
@@ -12,6 +15,9 @@ pub struct GLDevice{
     gl_context : sdl2::video::GLContext,
 
     active_pipeline : Option<Rc<Pipeline>>,
+
+    // Keeps the log alive while the debug callback points to it
+    debug_log : Option<DebugLogHandle>,
 }
 
 impl GLDevice {
@@ -24,6 +30,7 @@ impl GLDevice {
             GLDevice{
                 gl_context,
                 active_pipeline: None,
+                debug_log: None,
             }
         )
     }
@@ -54,37 +61,308 @@ impl Device for GLDevice {
     fn create_command_list(&self) -> Box<dyn CommandList> {
         Box::new(
             GLCommandList{
+                name: String::new(),
                 commands: Vec::new(),
-                active_pipeline: std::ptr::null()
+                active_pipeline: std::ptr::null(),
+                active_pass: None,
+                index_type: gl::UNSIGNED_INT,
+                index_offset: 0,
             }
         )
     }
 
+    fn create_render_target(&self, desc : &RenderTargetDesc) -> Result<RenderTargetHandle, String> {
+        Ok(Arc::new(GLRenderTarget::new(desc)?))
+    }
+
+    fn max_sample_count(&self) -> u32 {
+        let mut samples = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut samples);
+        }
+        samples.max(1) as u32
+    }
+
     /// Enabled the debug output and binds callbacks
-    fn enable_debug_layer(&self) -> Result<(), String > {
+    fn enable_debug_layer(&mut self, log : &DebugLogHandle, synchronous : bool) -> Result<(), String > {
+        if !gl::DebugMessageCallback::is_loaded() {
+            return Err("Debug output is not supported by the driver".to_string());
+        }
+
+        let user_param = &**log as *const Mutex<DebugLog>;
+        self.debug_log = Some(log.clone());
         unsafe{
             gl::Enable(gl::DEBUG_OUTPUT);
-            gl::DebugMessageCallback(callback, std::ptr::null());
+            gl::DebugMessageCallback(callback, user_param as *mut std::ffi::c_void);
+
+            // Every pushed and popped debug group would be reported back as a message
+            for &kind in &[gl::DEBUG_TYPE_PUSH_GROUP, gl::DEBUG_TYPE_POP_GROUP] {
+                gl::DebugMessageControl(gl::DONT_CARE, kind, gl::DONT_CARE, 0, std::ptr::null(), gl::FALSE);
+            }
         }
+        self.set_debug_synchronous(synchronous);
         Ok( () )
     }
+
+    fn set_debug_synchronous(&self, synchronous : bool) {
+        helpers::gl_set_enabled(gl::DEBUG_OUTPUT_SYNCHRONOUS, synchronous);
+    }
+}
+
+fn source_name(source : GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(gltype : GLenum) -> &'static str {
+    match gltype {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
+
+fn severity(severity : GLenum) -> Severity {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => Severity::High,
+        gl::DEBUG_SEVERITY_MEDIUM => Severity::Medium,
+        gl::DEBUG_SEVERITY_LOW => Severity::Low,
+        _ => Severity::Notification,
+    }
 }
 
-#[allow(unused_assignments)]
 extern "system" fn callback(
-    _source: u32,
-    _gltype: u32,
-    _id: u32,
-    severity: u32,
-    _length: i32,
+    source: u32,
+    gltype: u32,
+    id: u32,
+    severity_enum: u32,
+    length: i32,
     message: *const i8,
-    _user_param: *mut std::ffi::c_void,
+    user_param: *mut std::ffi::c_void,
 ) {
-    if severity != gl::DEBUG_SEVERITY_NOTIFICATION {
+    let text = unsafe {
+        if length >= 0 {
+            let bytes = std::slice::from_raw_parts(message as *const u8, length as usize);
+            String::from_utf8_lossy(bytes).trim_end().to_string()
+        } else {
+            std::ffi::CStr::from_ptr(message).to_string_lossy().trim_end().to_string()
+        }
+    };
+    let message = DebugMessage {
+        source: source_name(source),
+        kind: type_name(gltype),
+        severity: severity(severity_enum),
+        id,
+        text,
+        is_error: gltype == gl::DEBUG_TYPE_ERROR,
+        count: 1,
+    };
+
+    // The log is only locked briefly. Waiting for it could deadlock when the driver reports from
+    // inside a call made while it is locked, so the rare message arriving then is dropped rather
+    // than printed past the filter.
+    let log = unsafe { &*(user_param as *const Mutex<DebugLog>) };
+    if let Ok(mut log) = log.try_lock() {
+        log.push(message);
+    }
+}
+
+fn internal_format(format : Format) -> GLenum {
+    match format {
+        Format::Rgba8 => gl::RGBA8,
+        Format::Rgba16F => gl::RGBA16F,
+        Format::Rgba32F => gl::RGBA32F,
+        Format::R32F => gl::R32F,
+        Format::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        Format::Depth32F => gl::DEPTH_COMPONENT32F,
+    }
+}
+
+fn depth_attachment(format : Format) -> GLenum {
+    match format {
+        Format::Depth24Stencil8 => gl::DEPTH_STENCIL_ATTACHMENT,
+        _ => gl::DEPTH_ATTACHMENT,
+    }
+}
+
+/// Framebuffer object with a texture per attachment
+pub struct GLRenderTarget {
+    desc : RenderTargetDesc,
+    fbo : GLuint,
+    color : Vec<GLuint>,
+    depth : Option<GLuint>,
+}
+
+impl GLRenderTarget {
+    fn new(desc : &RenderTargetDesc) -> Result<GLRenderTarget, String> {
+        if desc.color_formats.iter().any(|f| f.is_depth()) || desc.depth_format.map(|f| !f.is_depth()).unwrap_or(false) {
+            return Err("Render target formats don't match their attachments".to_string());
+        }
+
+        let mut target = GLRenderTarget {
+            desc: desc.clone(),
+            fbo: 0,
+            color: Vec::new(),
+            depth: None,
+        };
+
+        let samples = desc.sample_count.max(1);
+        let create_texture = |format : Format| -> GLuint {
+            let mut texture = 0;
+            unsafe {
+                if samples > 1 {
+                    gl::CreateTextures(gl::TEXTURE_2D_MULTISAMPLE, 1, &mut texture);
+                    gl::TextureStorage2DMultisample(texture, samples as GLsizei, internal_format(format), desc.width as GLsizei, desc.height as GLsizei, gl::TRUE);
+                } else {
+                    gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+                    gl::TextureStorage2D(texture, 1, internal_format(format), desc.width as GLsizei, desc.height as GLsizei);
+                    gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                    gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                    gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+                    gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+                }
+            }
+            texture
+        };
+
         unsafe {
-            let string = std::ffi::CStr::from_ptr(message);
-            println!("{}", string.to_str().unwrap());
+            gl::CreateFramebuffers(1, &mut target.fbo);
+            stats::object_created(ObjectKind::Framebuffer, 1);
+
+            let mut draw_buffers = Vec::new();
+            for (i, format) in desc.color_formats.iter().enumerate() {
+                let texture = create_texture(*format);
+                stats::object_created(ObjectKind::Texture, 1);
+                let attachment = gl::COLOR_ATTACHMENT0 + i as GLenum;
+                gl::NamedFramebufferTexture(target.fbo, attachment, texture, 0);
+                target.color.push(texture);
+                draw_buffers.push(attachment);
+            }
+            gl::NamedFramebufferDrawBuffers(target.fbo, draw_buffers.len() as GLsizei, draw_buffers.as_ptr());
+
+            if let Some(format) = desc.depth_format {
+                let texture = create_texture(format);
+                stats::object_created(ObjectKind::Texture, 1);
+                gl::NamedFramebufferTexture(target.fbo, depth_attachment(format), texture, 0);
+                target.depth = Some(texture);
+            }
+
+            if !desc.label.is_empty() {
+                helpers::object_label(gl::FRAMEBUFFER, target.fbo, &desc.label);
+                for (i, texture) in target.color.iter().enumerate() {
+                    helpers::object_label(gl::TEXTURE, *texture, &format!("{} color {}", desc.label, i));
+                }
+                if let Some(depth) = target.depth {
+                    helpers::object_label(gl::TEXTURE, depth, &format!("{} depth", desc.label));
+                }
+            }
+
+            let status = gl::CheckNamedFramebufferStatus(target.fbo, gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Render target of {}x{} is incomplete (0x{:x})", desc.width, desc.height, status));
+            }
         }
+        Ok(target)
+    }
+}
+
+impl RenderTarget for GLRenderTarget {
+    fn desc(&self) -> &RenderTargetDesc {
+        &self.desc
+    }
+
+    fn native_handle(&self) -> u32 {
+        self.fbo
+    }
+
+    fn color_texture(&self, index : usize) -> Option<u32> {
+        self.color.get(index).cloned()
+    }
+}
+
+impl Drop for GLRenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(self.color.len() as GLsizei, self.color.as_ptr());
+            stats::object_deleted(ObjectKind::Framebuffer, 1);
+            stats::object_deleted(ObjectKind::Texture, self.color.len());
+            if let Some(depth) = &self.depth {
+                gl::DeleteTextures(1, depth);
+                stats::object_deleted(ObjectKind::Texture, 1);
+            }
+        }
+    }
+}
+
+/// Attachments of the target a render pass draws into
+struct PassTarget {
+    fbo : GLuint,
+    color : Vec<GLenum>,
+    depth : Option<GLenum>,
+    size : Option<(u32, u32)>,
+}
+
+impl PassTarget {
+    fn new(target : Option<&RenderTargetHandle>) -> PassTarget {
+        match target {
+            Some(t) => {
+                let desc = t.desc();
+                PassTarget {
+                    fbo: t.native_handle(),
+                    color: (0..desc.color_formats.len()).map(|i| gl::COLOR_ATTACHMENT0 + i as GLenum).collect(),
+                    depth: desc.depth_format.map(depth_attachment),
+                    size: Some((desc.width, desc.height)),
+                }
+            },
+            // The window framebuffer uses different attachment names
+            None => PassTarget {
+                fbo: 0,
+                color: vec![gl::COLOR],
+                depth: Some(gl::DEPTH),
+                size: None,
+            },
+        }
+    }
+
+    /// Attachments whose contents can be discarded
+    fn discarded(&self, color : bool, depth : bool) -> Vec<GLenum> {
+        let mut attachments = Vec::new();
+        if color {
+            attachments.extend_from_slice(&self.color);
+        }
+        if depth {
+            attachments.extend(self.depth);
+        }
+        attachments
+    }
+}
+
+/// Size in bytes of one index of the GL index type
+fn index_size(index_type : GLenum) -> usize {
+    match index_type {
+        gl::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+/// Triangles a draw of `count` vertices or indices produces
+fn triangle_count(topology : GLenum, count : u32) -> u64 {
+    match topology {
+        gl::TRIANGLES => (count / 3) as u64,
+        _ => 0,
     }
 }
 
@@ -93,19 +371,67 @@ unsafe trait GLCommand {
 } 
 
 struct GLCommandList {
+   name : String,
+
    commands : Vec<Box<dyn GLCommand>>, 
 
    // Unsafe pointer
    active_pipeline : *const Pipeline,
+
+   // Target and store operations of the render pass being recorded
+   active_pass : Option<(PassTarget, RenderPassDesc)>,
+
+   // Type and byte offset of the index buffer bound while recording, used by draw_indexed
+   index_type : GLenum,
+   index_offset : u32,
 }
 
 impl CommandList for GLCommandList {
     fn execute(&self, device : &DeviceHandle) {
+        let start = time::precise_time_s();
+        let counters = stats::begin_execute();
+        if !self.name.is_empty() {
+            helpers::push_debug_group(&self.name);
+        }
         for c in &self.commands {
             unsafe {
                 c.execute();
             }
         }
+        if !self.name.is_empty() {
+            helpers::pop_debug_group();
+        }
+        stats::end_execute(counters, &self.name, self.commands.len(), ((time::precise_time_s() - start) * 1000.0) as f32);
+    }
+
+    fn set_name(&mut self, name : &str) {
+        self.name = name.to_string();
+    }
+
+    fn push_debug_group(&mut self, name : &str) {
+        struct Cmd {
+            name : String,
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                helpers::push_debug_group(&self.name);
+            }
+        }
+
+        self.commands.push(Box::new(Cmd { name: name.to_string() }));
+    }
+
+    fn pop_debug_group(&mut self) {
+        struct Cmd;
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                helpers::pop_debug_group();
+            }
+        }
+
+        self.commands.push(Box::new(Cmd));
     }
 
     fn clear(&mut self, color : [f32;4], depth : Option<f32>) {
@@ -137,6 +463,147 @@ impl CommandList for GLCommandList {
         }));
     }
 
+    fn begin_render_pass(&mut self, target : Option<&RenderTargetHandle>, desc : &RenderPassDesc) {
+        debug_assert!(self.active_pass.is_none(), "Render passes can't be nested");
+
+        struct Cmd {
+            fbo : GLuint,
+            size : Option<(u32, u32)>,
+            color_count : usize,
+            discard : Vec<GLenum>,
+            clear_color : Option<[f32; 4]>,
+            clear_depth : Option<f32>,
+            // Keeps the target alive while the command list references it
+            _target : Option<RenderTargetHandle>,
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+                stats::record_state_change();
+                if let Some((width, height)) = self.size {
+                    gl::Viewport(0, 0, width as GLsizei, height as GLsizei);
+                }
+                if !self.discard.is_empty() {
+                    gl::InvalidateNamedFramebufferData(self.fbo, self.discard.len() as GLsizei, self.discard.as_ptr());
+                }
+                if let Some(color) = self.clear_color {
+                    for i in 0..self.color_count {
+                        gl::ClearNamedFramebufferfv(self.fbo, gl::COLOR, i as GLint, color.as_ptr());
+                    }
+                }
+                if let Some(depth) = self.clear_depth {
+                    gl::DepthMask(gl::TRUE);
+                    gl::ClearNamedFramebufferfv(self.fbo, gl::DEPTH, 0, &depth);
+                }
+            }
+        }
+
+        let pass = PassTarget::new(target);
+        let discard_color = match desc.color_load { LoadOp::DontCare => true, _ => false };
+        let discard_depth = match desc.depth_load { LoadOp::DontCare => true, _ => false };
+
+        self.commands.push(Box::new(Cmd {
+            fbo: pass.fbo,
+            size: pass.size,
+            color_count: pass.color.len(),
+            discard: pass.discarded(discard_color, discard_depth),
+            clear_color: match desc.color_load { LoadOp::Clear(c) => Some(c), _ => None },
+            clear_depth: match desc.depth_load { LoadOp::Clear(d) if pass.depth.is_some() => Some(d), _ => None },
+            _target: target.cloned(),
+        }));
+        self.active_pass = Some((pass, *desc));
+    }
+
+    fn end_render_pass(&mut self) {
+        struct Cmd {
+            fbo : GLuint,
+            discard : Vec<GLenum>,
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                if !self.discard.is_empty() {
+                    gl::InvalidateNamedFramebufferData(self.fbo, self.discard.len() as GLsizei, self.discard.as_ptr());
+                }
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                stats::record_state_change();
+            }
+        }
+
+        // A pass started by another command list keeps all its attachments
+        let (pass, desc) = self.active_pass.take().unwrap_or_else(|| (PassTarget::new(None), RenderPassDesc::load()));
+        let discard_color = match desc.color_store { StoreOp::DontCare => true, _ => false };
+        let discard_depth = match desc.depth_store { StoreOp::DontCare => true, _ => false };
+        self.commands.push(Box::new(Cmd {
+            fbo: pass.fbo,
+            discard: pass.discarded(discard_color, discard_depth),
+        }));
+    }
+
+    fn set_viewport(&mut self, rect : Rect) {
+        struct Cmd {
+            rect : Rect,
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                gl::Viewport(self.rect.x, self.rect.y, self.rect.width as GLsizei, self.rect.height as GLsizei);
+                stats::record_state_change();
+            }
+        }
+
+        self.commands.push(Box::new(Cmd { rect }));
+    }
+
+    fn set_scissor(&mut self, rect : Option<Rect>) {
+        struct Cmd {
+            rect : Option<Rect>,
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                match self.rect {
+                    Some(r) => {
+                        gl::Enable(gl::SCISSOR_TEST);
+                        gl::Scissor(r.x, r.y, r.width as GLsizei, r.height as GLsizei);
+                    },
+                    None => gl::Disable(gl::SCISSOR_TEST),
+                }
+                stats::record_state_change();
+            }
+        }
+
+        self.commands.push(Box::new(Cmd { rect }));
+    }
+
+    fn resolve(&mut self, source : &RenderTargetHandle, destination : Option<&RenderTargetHandle>, rect : Rect) {
+        struct Cmd {
+            source : GLuint,
+            destination : GLuint,
+            rect : Rect,
+            // Keep the targets alive while the command list references them
+            _targets : (RenderTargetHandle, Option<RenderTargetHandle>),
+        }
+
+        unsafe impl GLCommand for Cmd {
+            unsafe fn execute(&self) {
+                let (x0, y0) = (self.rect.x, self.rect.y);
+                let (x1, y1) = (x0 + self.rect.width as GLint, y0 + self.rect.height as GLint);
+                gl::NamedFramebufferReadBuffer(self.source, gl::COLOR_ATTACHMENT0);
+                gl::BlitNamedFramebuffer(self.source, self.destination, x0, y0, x1, y1, x0, y0, x1, y1, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            }
+        }
+
+        self.commands.push(Box::new(Cmd {
+            source: source.native_handle(),
+            destination: destination.map(|d| d.native_handle()).unwrap_or(0),
+            rect,
+            _targets: (source.clone(), destination.cloned()),
+        }));
+    }
+
     fn draw(&mut self, vertex_count : u32, instance_count : u32, first_vertex : u32, first_instance : u32){
         debug_assert!(self.active_pipeline != std::ptr::null());
 
@@ -152,6 +619,7 @@ impl CommandList for GLCommandList {
             unsafe fn execute(&self) {
                 // #TODO : Implement support for draw modes taken from the currently bound pipelines?
                 gl::DrawArrays(self.topology, self.first_vertex as GLsizei,self.vertex_count as GLsizei);
+                stats::record_draw(self.vertex_count as u64, triangle_count(self.topology, self.vertex_count));
             }
         }
 
@@ -175,12 +643,16 @@ impl CommandList for GLCommandList {
             vertex_offset : u32,
             first_instance : u32,
             topology: GLenum,
+            index_type : GLenum,
+            // Byte offset of the first index in the index buffer
+            offset : usize,
         }; 
 
         unsafe impl GLCommand for Command {
             unsafe fn execute(&self) {
                 // #TODO implement support for other draw modes...
-                gl::DrawElements(self.topology, self.index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null());
+                gl::DrawElements(self.topology, self.index_count as GLsizei, self.index_type, self.offset as *const std::ffi::c_void);
+                stats::record_draw(self.index_count as u64, triangle_count(self.topology, self.index_count));
             }
         }
 
@@ -194,7 +666,9 @@ impl CommandList for GLCommandList {
             first_index,
             vertex_offset,
             first_instance,
-            topology: topo
+            topology: topo,
+            index_type: self.index_type,
+            offset: self.index_offset as usize + first_index as usize * index_size(self.index_type),
         }));
     }
 
@@ -203,6 +677,8 @@ impl CommandList for GLCommandList {
             program : GLuint,
             blend_enabled : bool,
             depth_test : bool,
+            depth_func : GLenum,
+            depth_write : bool,
             fill_mode : GLenum,
         }; 
 
@@ -212,11 +688,14 @@ impl CommandList for GLCommandList {
                 // Do the pipeline it's state
                 helpers::gl_set_enabled(gl::DEPTH_TEST,self.depth_test);
                 helpers::gl_set_enabled(gl::BLEND,self.blend_enabled);
+                gl::DepthFunc(self.depth_func);
+                gl::DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE });
                 
                 gl::CullFace(gl::BACK);
                 gl::PolygonMode(gl::FRONT_AND_BACK, self.fill_mode);
 
                 gl::UseProgram(self.program);
+                stats::record_state_change();
 
                 // Flush uniforms?
             }
@@ -224,16 +703,23 @@ impl CommandList for GLCommandList {
 
 
         self.active_pipeline =  Arc::into_raw(pipeline.clone());
-        use crate::pipeline::FillMode;
+        use crate::pipeline::{DepthCompare, FillMode};
         let fill_mode = match pipeline.fill_mode() {
             FillMode::Fill => gl::FILL,
             FillMode::Lines => gl::LINE,
         };
+        let depth_func = match pipeline.depth_compare() {
+            DepthCompare::Less => gl::LESS,
+            DepthCompare::LessEqual => gl::LEQUAL,
+            DepthCompare::Greater => gl::GREATER,
+        };
 
         self.commands.push(Box::new( Command {
             program: pipeline.program(),
             blend_enabled: pipeline.blend_enabled(),
             depth_test: pipeline.depth_test(),
+            depth_func,
+            depth_write: pipeline.depth_write(),
             fill_mode
         }));
     }
@@ -247,6 +733,7 @@ impl CommandList for GLCommandList {
         unsafe impl GLCommand for Command {
             unsafe fn execute(&self) {
                 gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+                stats::record_state_change();
             }
         }
 
@@ -257,6 +744,26 @@ impl CommandList for GLCommandList {
     }
 
     fn bind_index_buffer(&mut self, buffer : &BufferHandle, offset : u32, index_type : IndexType) {
+        struct Command {
+            buffer : GLuint
+        };
 
+        unsafe impl GLCommand for Command {
+            unsafe fn execute(&self) {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.buffer);
+                stats::record_state_change();
+            }
+        }
+
+        // The offset and type are parameters of the GL draw call, not of the binding
+        self.index_type = match index_type {
+            IndexType::UnsignedShort => gl::UNSIGNED_SHORT,
+            IndexType::UnsignedInt => gl::UNSIGNED_INT,
+        };
+        self.index_offset = offset;
+
+        self.commands.push(Box::new( Command {
+            buffer: *buffer
+        }));
     }
 }
\ No newline at end of file
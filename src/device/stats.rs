@@ -0,0 +1,159 @@
+/// Rendering statistics
+/// ---
+/// Counters filled in while command lists execute. OpenGL is only used from the main thread so
+/// they are kept per thread, which lets code that talks to OpenGL directly (uniform uploads, the
+/// IMGUI renderer, GL objects created outside of the device) report into them as well.
+use std::cell::RefCell;
+
+/// Work submitted to the GPU
+#[derive(Clone, Copy, Default)]
+pub struct DrawStats {
+    pub draw_calls : u64,
+    pub triangles : u64,
+    pub vertices : u64,
+    /// Pipeline, buffer, framebuffer, viewport and scissor changes
+    pub state_changes : u64,
+    pub uniform_uploads : u64,
+}
+
+impl DrawStats {
+    fn since(&self, start : &DrawStats) -> DrawStats {
+        DrawStats {
+            draw_calls: self.draw_calls - start.draw_calls,
+            triangles: self.triangles - start.triangles,
+            vertices: self.vertices - start.vertices,
+            state_changes: self.state_changes - start.state_changes,
+            uniform_uploads: self.uniform_uploads - start.uniform_uploads,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ObjectKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    Framebuffer,
+    Program,
+    Query,
+}
+
+impl ObjectKind {
+    pub const ALL : [ObjectKind; 6] = [
+        ObjectKind::Buffer,
+        ObjectKind::VertexArray,
+        ObjectKind::Texture,
+        ObjectKind::Framebuffer,
+        ObjectKind::Program,
+        ObjectKind::Query,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Buffer => "Buffers",
+            ObjectKind::VertexArray => "Vertex arrays",
+            ObjectKind::Texture => "Textures",
+            ObjectKind::Framebuffer => "Framebuffers",
+            ObjectKind::Program => "Programs",
+            ObjectKind::Query => "Queries",
+        }
+    }
+
+    fn index(self) -> usize {
+        ObjectKind::ALL.iter().position(|k| *k == self).unwrap()
+    }
+}
+
+/// One `CommandList::execute`
+#[derive(Clone)]
+pub struct ExecuteStats {
+    pub name : String,
+    pub commands : usize,
+    pub draw : DrawStats,
+    pub cpu_ms : f32,
+}
+
+/// Statistics of a finished frame
+#[derive(Clone, Default)]
+pub struct FrameStats {
+    /// Everything drawn in the frame, including what was not recorded in a command list
+    pub draw : DrawStats,
+    pub executes : Vec<ExecuteStats>,
+    /// Live objects per `ObjectKind`, in the order of `ObjectKind::ALL`
+    pub objects : [i64; 6],
+}
+
+impl FrameStats {
+    pub fn objects(&self, kind : ObjectKind) -> i64 {
+        self.objects[kind.index()]
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    draw : DrawStats,
+    executes : Vec<ExecuteStats>,
+    objects : [i64; 6],
+    last_frame : FrameStats,
+}
+
+thread_local! {
+    static COUNTERS : RefCell<Counters> = RefCell::new(Counters::default());
+}
+
+fn with<R>(f : impl FnOnce(&mut Counters) -> R) -> R {
+    COUNTERS.with(|c| f(&mut c.borrow_mut()))
+}
+
+pub fn record_draw(vertices : u64, triangles : u64) {
+    with(|c| {
+        c.draw.draw_calls += 1;
+        c.draw.vertices += vertices;
+        c.draw.triangles += triangles;
+    });
+}
+
+pub fn record_state_change() {
+    with(|c| c.draw.state_changes += 1);
+}
+
+pub fn record_uniform_upload() {
+    with(|c| c.draw.uniform_uploads += 1);
+}
+
+pub fn object_created(kind : ObjectKind, count : usize) {
+    with(|c| c.objects[kind.index()] += count as i64);
+}
+
+pub fn object_deleted(kind : ObjectKind, count : usize) {
+    with(|c| c.objects[kind.index()] -= count as i64);
+}
+
+/// Counters at the start of a command list, passed back to `end_execute`
+pub fn begin_execute() -> DrawStats {
+    with(|c| c.draw)
+}
+
+pub fn end_execute(start : DrawStats, name : &str, commands : usize, cpu_ms : f32) {
+    with(|c| {
+        let draw = c.draw.since(&start);
+        c.executes.push(ExecuteStats { name: name.to_string(), commands, draw, cpu_ms });
+    });
+}
+
+/// Finishes the frame and starts counting the next one
+pub fn end_frame() {
+    with(|c| {
+        c.last_frame = FrameStats {
+            draw: c.draw,
+            executes: std::mem::replace(&mut c.executes, Vec::new()),
+            objects: c.objects,
+        };
+        c.draw = DrawStats::default();
+    });
+}
+
+/// Statistics of the last finished frame
+pub fn last_frame() -> FrameStats {
+    with(|c| c.last_frame.clone())
+}
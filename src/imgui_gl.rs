@@ -2,6 +2,7 @@
 /// 
 use imgui::ImGui;
 use crate::helpers;
+use crate::device::stats::{self, ObjectKind};
 use gl::types::*;
 
 pub struct ImGuiGl {
@@ -10,6 +11,7 @@ pub struct ImGuiGl {
     program : crate::pipeline::Pipeline,
     vertex_buffer : GLuint,
     index_buffer : GLuint,
+    vao : GLuint,
 }
 
 impl Drop for ImGuiGl {
@@ -18,7 +20,13 @@ impl Drop for ImGuiGl {
             gl::DeleteProgram(self.program.program());
             gl::DeleteBuffers(1, &self.vertex_buffer);
             gl::DeleteBuffers(1, &self.index_buffer);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteTextures(self.font_textures.len() as GLsizei, self.font_textures.as_ptr());
         }
+        stats::object_deleted(ObjectKind::Program, 1);
+        stats::object_deleted(ObjectKind::Buffer, 2);
+        stats::object_deleted(ObjectKind::VertexArray, 1);
+        stats::object_deleted(ObjectKind::Texture, self.font_textures.len());
     }
 }
 impl ImGuiGl {
@@ -46,7 +54,9 @@ impl ImGuiGl {
             let height = font_tex.height as i32;
             unsafe{
                 gl::GenTextures(1,&mut texture);
+                stats::object_created(ObjectKind::Texture, 1);
                 gl::BindTexture(gl::TEXTURE_2D, texture);
+                helpers::object_label(gl::TEXTURE, texture, "imgui font");
                 gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
                 gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
 
@@ -67,12 +77,18 @@ impl ImGuiGl {
         let mut vtx = 0;
         let mut idx = 0;
         unsafe{
-            gl::GenBuffers(1,&mut vtx);
-            gl::GenBuffers(1,&mut idx);
+            gl::CreateBuffers(1,&mut vtx);
+            gl::CreateBuffers(1,&mut idx);
         }
+        stats::object_created(ObjectKind::Buffer, 2);
+        helpers::object_label(gl::BUFFER, vtx, "imgui vertices");
+        helpers::object_label(gl::BUFFER, idx, "imgui indices");
+        let vao = unsafe { Self::setup_vao(vtx) };
+        helpers::object_label(gl::VERTEX_ARRAY, vao, "imgui vertex layout");
 
         // Create the program
-        let program = crate::pipeline::Pipeline::create_simple(include_bytes!("../shaders/imgui.vert"), include_bytes!("../shaders/imgui.frag")).unwrap();
+        let mut program = crate::pipeline::Pipeline::create_simple(include_bytes!("../shaders/imgui.vert"), include_bytes!("../shaders/imgui.frag")).unwrap();
+        program.set_label("imgui");
         helpers::log_gl_errors();
         program.flush();
         helpers::log_gl_errors();
@@ -82,9 +98,47 @@ impl ImGuiGl {
             program: program,
             vertex_buffer: vtx,
             index_buffer: idx,
+            vao,
         }
     }
 
+    /// Vertex layout of `ImDrawVert`, created once and reused every frame
+    unsafe fn setup_vao(vertex_buffer : GLuint) -> GLuint {
+        let mut vao = 0;
+        gl::GenVertexArrays(1,&mut vao);
+        stats::object_created(ObjectKind::VertexArray, 1);
+
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+
+        use imgui::ImDrawVert;
+        let struct_size = std::mem::size_of::<ImDrawVert>() as i32;
+
+        gl::EnableVertexArrayAttrib(vao, 0);
+        gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, struct_size, std::ptr::null());
+
+        gl::EnableVertexArrayAttrib(vao, 1);
+        gl::VertexAttribPointer(
+            1,
+            2,
+            gl::FLOAT,
+            gl::FALSE,
+            struct_size,
+            (2 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
+        );
+
+        gl::EnableVertexArrayAttrib(vao, 2);
+        gl::VertexAttribPointer(
+            2,
+            4,
+            gl::UNSIGNED_BYTE,
+            gl::TRUE,
+            struct_size,
+            (4 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
+        );
+        vao
+    }
+
     pub fn handle_event(&mut self, imgui : &mut ImGui, event : &sdl2::event::Event){
         use sdl2::event::Event;
         use sdl2::mouse::MouseButton;
@@ -145,6 +199,7 @@ impl ImGuiGl {
 
     pub fn render(&mut self, proj : &na::Mat4, ui : imgui::Ui) {
         let font_textures = &self.font_textures;
+        helpers::push_debug_group("imgui");
 
         use crate::helpers::gl_set_enabled;
         let last_blending_enabled = gl_set_enabled(gl::BLEND, true);
@@ -162,39 +217,9 @@ impl ImGuiGl {
         let imgui::FrameSize{logical_size: (fb_width, fb_height), ..} = ui.frame_size();
         let _result = ui.render::<_, ()>(|_, data|{
 
-            let mut vao = 0;
+            let vao = self.vao;
             unsafe{
-                gl::GenVertexArrays(1,&mut vao);
-
                 gl::BindVertexArray(vao);
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
-
-                use imgui::ImDrawVert;
-                let struct_size = std::mem::size_of::<ImDrawVert>() as i32;
-
-                gl::EnableVertexArrayAttrib(vao, 0);
-                gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, struct_size, std::ptr::null());
-
-                gl::EnableVertexArrayAttrib(vao, 1);
-                gl::VertexAttribPointer(
-                    1,
-                    2,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    struct_size,
-                    (2 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-                );
-
-                gl::EnableVertexArrayAttrib(vao, 2);
-                gl::VertexAttribPointer(
-                    2,
-                    4,
-                    gl::UNSIGNED_BYTE,
-                    gl::TRUE,
-                    struct_size,
-                    (4 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-                );
-
                 gl::UseProgram(self.program.program());
                 gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer);
                 use crate::pipeline::ShaderUniform;
@@ -240,6 +265,7 @@ impl ImGuiGl {
                             (offset*2) as *const std::ffi::c_void,
                         );
                     }
+                    stats::record_draw(cmd.elem_count as u64, (cmd.elem_count / 3) as u64);
                     offset += cmd.elem_count;
                 }
             }
@@ -252,6 +278,7 @@ impl ImGuiGl {
         gl_set_enabled(gl::DEPTH_TEST, last_depth_test_enabled);
         gl_set_enabled(gl::CULL_FACE, last_culling_enabled);
         gl_set_enabled(gl::SCISSOR_TEST, last_enabled_scissor_test);
+        helpers::pop_debug_group();
     }
 }
 
@@ -8,43 +8,919 @@ extern crate nalgebra_glm as na;
 extern crate sdl2;
 extern crate time;
 extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+extern crate png;
+extern crate mikktspace;
+extern crate memmap;
+extern crate gif;
+extern crate deflate;
+extern crate crc32fast;
 
 // MODULES
 mod pipeline;
 mod device;
 mod imgui_gl;
+mod ui;
+mod settings;
+mod cli;
+mod capture;
+mod mesh;
+mod mesh_cache;
+mod mesh_edges;
+mod mesh_loader;
+mod mesh_math;
+mod mesh_processing;
+mod profiler;
+mod benchmark;
+mod mesh_analysis;
+mod texture;
+mod turntable;
+mod vector_export;
 
 // Imports
 use imgui::ImGui;
 use time::PreciseTime;
 
-use gl::types::*;
-
 use std::sync::Arc;
+use std::cell::RefCell;
 
 
 use crate::pipeline::{ ShaderUniform, Pipeline};
+use crate::settings::Settings;
+use crate::mesh::GpuMesh;
+use crate::device::{CommandList, DeviceHandle, RenderPassDesc, RenderTargetDesc, RenderTargetHandle, Rect};
 
 // Mode to control what program to use
-enum WireframeMode {
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireframeMode {
     None,
     SinglePass,
     SinglePassCorrection,
     MultiPass,
+    UvSpace,
+    Heatmap,
+    ScreenSpace,
+    FeatureEdges,
 }
 impl WireframeMode{
+    pub const ALL : [WireframeMode; 8] = [
+        WireframeMode::None,
+        WireframeMode::SinglePass,
+        WireframeMode::SinglePassCorrection,
+        WireframeMode::MultiPass,
+        WireframeMode::UvSpace,
+        WireframeMode::Heatmap,
+        WireframeMode::ScreenSpace,
+        WireframeMode::FeatureEdges,
+    ];
+
+    /// Name used in the config file and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            WireframeMode::None => "none",
+            WireframeMode::SinglePass => "single_pass",
+            WireframeMode::SinglePassCorrection => "single_pass_correction",
+            WireframeMode::MultiPass => "multi_pass",
+            WireframeMode::UvSpace => "uv_space",
+            WireframeMode::Heatmap => "heatmap",
+            WireframeMode::ScreenSpace => "screen_space",
+            WireframeMode::FeatureEdges => "feature_edges",
+        }
+    }
+
+    /// Label shown in the draw mode combo
+    pub fn label(self) -> &'static imgui::ImStr {
+        use imgui::im_str;
+        match self {
+            WireframeMode::None => im_str!("Default"),
+            WireframeMode::SinglePass => im_str!("Singlepass"),
+            WireframeMode::SinglePassCorrection => im_str!("Singlepass correction"),
+            WireframeMode::MultiPass => im_str!("Multipass"),
+            WireframeMode::UvSpace => im_str!("UV space"),
+            WireframeMode::Heatmap => im_str!("Quality heatmap"),
+            WireframeMode::ScreenSpace => im_str!("Screen-space edges"),
+            WireframeMode::FeatureEdges => im_str!("Feature edges"),
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            WireframeMode::None => "Solid shading without wireframe",
+            WireframeMode::SinglePass => "Edge distance computed in a geometry shader",
+            WireframeMode::SinglePassCorrection => "Edge distance with screen-space height correction",
+            WireframeMode::MultiPass => "Solid pass followed by a glPolygonMode(GL_LINE) pass",
+            WireframeMode::UvSpace => "Triangles laid out at their texture coordinates",
+            WireframeMode::Heatmap => "Triangle quality heatmap with a single pass wireframe",
+            WireframeMode::ScreenSpace => "Edge filter over a depth and normal G-buffer",
+            WireframeMode::FeatureEdges => "Boundary and crease edges drawn as quad lines",
+        }
+    }
+
+    pub fn from_name(name : &str) -> Option<WireframeMode> {
+        WireframeMode::ALL.iter().cloned().find(|m| m.name() == name)
+    }
+
     fn from_int(mode : u32) -> WireframeMode {
         match mode {
             0 => WireframeMode::None,
             1 => WireframeMode::SinglePass,
             2 => WireframeMode::SinglePassCorrection,
             3 => WireframeMode::MultiPass,
+            4 => WireframeMode::UvSpace,
+            5 => WireframeMode::Heatmap,
+            6 => WireframeMode::ScreenSpace,
+            7 => WireframeMode::FeatureEdges,
             _ => WireframeMode::None,
         }
     }
+
+    fn to_int(self) -> u32 {
+        match self {
+            WireframeMode::None => 0,
+            WireframeMode::SinglePass => 1,
+            WireframeMode::SinglePassCorrection => 2,
+            WireframeMode::MultiPass => 3,
+            WireframeMode::UvSpace => 4,
+            WireframeMode::Heatmap => 5,
+            WireframeMode::ScreenSpace => 6,
+            WireframeMode::FeatureEdges => 7,
+        }
+    }
+}
+
+/// Pipelines used by the different wireframe modes
+struct Pipelines {
+    default : Arc<Pipeline>,
+    wireframe : Arc<Pipeline>,
+    singlepass : Arc<Pipeline>,
+    vectors : Arc<Pipeline>,
+    uv_background : Arc<Pipeline>,
+    uv_wireframe : Arc<Pipeline>,
+    heatmap : Arc<Pipeline>,
+    gbuffer : Arc<Pipeline>,
+    edges : Arc<Pipeline>,
+    lines : Arc<Pipeline>,
+    lines_hidden : Arc<Pipeline>,
+}
+
+/// Command lists for each wireframe mode, these need to be recorded again when the mesh changes
+struct CommandLists {
+    default : Box<dyn CommandList>,
+    singlepass : Box<dyn CommandList>,
+    multipass : Box<dyn CommandList>,
+    vectors : Box<dyn CommandList>,
+    uv : Box<dyn CommandList>,
+    heatmap : Box<dyn CommandList>,
+    gbuffer : Box<dyn CommandList>,
+    edges : Box<dyn CommandList>,
+    lines : Box<dyn CommandList>,
+    lines_hidden : Box<dyn CommandList>,
+}
+
+fn record_command_lists(gl : &DeviceHandle, pipelines : &Pipelines, mesh : &GpuMesh) -> CommandLists {
+    let clear_color = [0.3,0.3,0.3,1.0];
+    let mut default_list = gl.borrow().create_command_list();
+    {
+        default_list.set_name("solid pass");
+        default_list.clear(clear_color, None);
+        default_list.bind_pipeline(&pipelines.default);
+        default_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        default_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        default_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+    }
+
+    let mut singlepass_list = gl.borrow().create_command_list();
+    {
+        singlepass_list.set_name("single pass wireframe");
+        singlepass_list.clear(clear_color, None);
+        singlepass_list.bind_pipeline(&pipelines.singlepass);
+        singlepass_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        singlepass_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        singlepass_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+    }
+
+    let mut multipass_list = gl.borrow().create_command_list();
+    {
+        multipass_list.set_name("multi pass wireframe");
+        multipass_list.clear(clear_color, None);
+        multipass_list.push_debug_group("solid pass");
+        multipass_list.bind_pipeline(&pipelines.default);
+        multipass_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        multipass_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        multipass_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+        multipass_list.pop_debug_group();
+
+        multipass_list.push_debug_group("wireframe pass");
+        multipass_list.bind_pipeline(&pipelines.wireframe);
+        multipass_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+        multipass_list.pop_debug_group();
+    }
+
+    // Debug vectors are drawn from the vertices, no indices needed
+    let mut vectors_list = gl.borrow().create_command_list();
+    {
+        vectors_list.set_name("vector overlay");
+        vectors_list.bind_pipeline(&pipelines.vectors);
+        vectors_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        vectors_list.draw(mesh.vertex_count, 1, 0, 0);
+    }
+
+    let mut uv_list = gl.borrow().create_command_list();
+    {
+        uv_list.set_name("uv layout");
+        uv_list.clear(clear_color, None);
+        uv_list.push_debug_group("background pass");
+        uv_list.bind_pipeline(&pipelines.uv_background);
+        uv_list.draw(3, 1, 0, 0);
+        uv_list.pop_debug_group();
+
+        uv_list.push_debug_group("wireframe pass");
+        uv_list.bind_pipeline(&pipelines.uv_wireframe);
+        uv_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        uv_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        uv_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+        uv_list.pop_debug_group();
+    }
+
+    let mut heatmap_list = gl.borrow().create_command_list();
+    {
+        heatmap_list.set_name("heatmap pass");
+        heatmap_list.clear(clear_color, None);
+        heatmap_list.bind_pipeline(&pipelines.heatmap);
+        heatmap_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        heatmap_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        heatmap_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+    }
+
+    // The G-buffer pass is cleared by its render pass
+    let mut gbuffer_list = gl.borrow().create_command_list();
+    {
+        gbuffer_list.set_name("gbuffer pass");
+        gbuffer_list.bind_pipeline(&pipelines.gbuffer);
+        gbuffer_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        gbuffer_list.bind_index_buffer(&mesh.index_buffer, 0, device::IndexType::UnsignedInt);
+        gbuffer_list.draw_indexed(mesh.index_count, 1, 0,0,0);
+    }
+
+    let mut edges_list = gl.borrow().create_command_list();
+    {
+        edges_list.set_name("edge detection pass");
+        edges_list.bind_pipeline(&pipelines.edges);
+        edges_list.draw(3, 1, 0, 0);
+    }
+
+    // Quad lines are expanded from the edge list, drawn on top of the default pass
+    let mut lines_list = gl.borrow().create_command_list();
+    {
+        lines_list.set_name("line pass");
+        lines_list.bind_pipeline(&pipelines.lines);
+        lines_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        lines_list.bind_index_buffer(&mesh.edge_index_buffer, 0, device::IndexType::UnsignedInt);
+        lines_list.draw_indexed(mesh.edge_count * 2, 1, 0,0,0);
+    }
+
+    let mut lines_hidden_list = gl.borrow().create_command_list();
+    {
+        lines_hidden_list.set_name("hidden line pass");
+        lines_hidden_list.bind_pipeline(&pipelines.lines_hidden);
+        lines_hidden_list.bind_vertex_buffers(0, 1, &[mesh.vertex_buffer], &[0]);
+        lines_hidden_list.bind_index_buffer(&mesh.edge_index_buffer, 0, device::IndexType::UnsignedInt);
+        lines_hidden_list.draw_indexed(mesh.edge_count * 2, 1, 0,0,0);
+    }
+
+    CommandLists {
+        default: default_list,
+        singlepass: singlepass_list,
+        multipass: multipass_list,
+        vectors: vectors_list,
+        uv: uv_list,
+        heatmap: heatmap_list,
+        gbuffer: gbuffer_list,
+        edges: edges_list,
+        lines: lines_list,
+        lines_hidden: lines_hidden_list,
+    }
+}
+
+/// Everything needed to render the scene, borrowed for the duration of a frame
+#[derive(Clone, Copy)]
+struct Scene<'a> {
+    gl : &'a DeviceHandle,
+    pipelines : &'a Pipelines,
+    command_lists : &'a CommandLists,
+    gpu_mesh : &'a GpuMesh,
+    settings : &'a Settings,
+    uv_texture : &'a Option<texture::Texture>,
+    /// Normals and depth of the screen-space mode, one per target size, most recently used last
+    gbuffer : &'a RefCell<Vec<RenderTargetHandle>>,
+    profiler : &'a RefCell<profiler::Profiler>,
+}
+
+impl<'a> Scene<'a> {
+    /// Runs `f` inside a profiler scope and a debug group of the same name
+    fn profile<R>(&self, name : &str, f : impl FnOnce() -> R) -> R {
+        helpers::push_debug_group(name);
+        self.profiler.borrow_mut().begin_scope(name);
+        let result = f();
+        self.profiler.borrow_mut().end_scope();
+        helpers::pop_debug_group();
+        result
+    }
+
+    /// Executes a command list inside a profiler scope
+    fn execute(&self, name : &str, list : &dyn CommandList) {
+        self.profile(name, || list.execute(self.gl));
+    }
+}
+
+/// Camera of a rendered image. Captures above window resolution render the image in tiles,
+/// `tile` selects the part of the image that ends up in the viewport.
+#[derive(Clone)]
+struct SceneView {
+    model : na::Mat4,
+    view_projection : na::Mat4,
+    /// Size of the full image in window pixels
+    width : u32,
+    height : u32,
+    /// Scale (xy) and offset (zw) in normalized device coordinates
+    tile : [f32; 4],
+    /// Multiplier for the line widths that are given in pixels
+    pixel_scale : f32,
+    /// Use the anti-aliasing options, disabled for the reference half of the comparison
+    antialiased : bool,
+    /// Target the scene is rendered into, `None` is the window
+    target : Option<RenderTargetHandle>,
+    /// Scissor rectangle that is active on the target
+    scissor : Option<Rect>,
+    /// Seconds since the start, animates the stroke patterns
+    time : f32,
+}
+
+const FULL_TILE : [f32; 4] = [1.0, 1.0, 0.0, 0.0];
+
+/// Applies the tile transform to a uv to normalized device coordinates transform
+fn tile_uv_transform(uv_transform : [f32; 4], tile : [f32; 4]) -> [f32; 4] {
+    [
+        uv_transform[0] * tile[0],
+        uv_transform[1] * tile[1],
+        uv_transform[2] * tile[0] + tile[2],
+        uv_transform[3] * tile[1] + tile[3],
+    ]
+}
+
+/// Renders the mesh with the active wireframe mode and the vector overlay into the bound framebuffer
+fn render_scene(scene : &Scene, view : &SceneView) {
+    scene.profile(scene.settings.render.mode.name(), || render_mode(scene, view));
+}
+
+fn render_mode(scene : &Scene, view : &SceneView) {
+    let settings = scene.settings;
+    let render = &settings.render;
+    let model = view.model;
+    let tile = view.tile;
+    let tile_matrix = na::Mat4::new(
+        tile[0], 0.0, 0.0, tile[2],
+        0.0, tile[1], 0.0, tile[3],
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    );
+    let view_projection = tile_matrix * view.view_projection;
+    // The corrected edge distance is measured in clip space, which grows with the tile zoom
+    let clip_scale = (tile[0] + tile[1]) * 0.5;
+    let no_antialiasing = settings::AntiAliasingSettings::default();
+    let antialiasing = if view.antialiased { &settings.antialiasing } else { &no_antialiasing };
+
+    unsafe {
+        // Render our loaded mesh
+        gl::BindVertexArray(scene.gpu_mesh.vao);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, scene.gpu_mesh.index_buffer);
+
+        // Execute rendering code for certain modes
+        match render.mode {
+            WireframeMode::None => {
+                // First draw
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+
+                // Manually update some uniforms
+                let p = &scene.pipelines.default;
+                set_transform_uniforms(p, &model, &view_projection);
+                set_material_uniforms(p, render);
+                p.flush();
+
+                // Execute the command list
+                scene.execute("shading", &*scene.command_lists.default);
+            },
+            WireframeMode::SinglePass | WireframeMode::SinglePassCorrection => {
+                let p = &scene.pipelines.singlepass;
+                match render.mode {
+                    WireframeMode::SinglePassCorrection => {
+                        p.set_uniform("u_correction", ShaderUniform::Int(1));
+                        p.set_uniform("u_line_thickness", ShaderUniform::Float(render.line_thickness * clip_scale));
+                        p.set_uniform("u_falloff", ShaderUniform::Float(render.falloff * clip_scale));
+                    }
+                    _ => {
+                        p.set_uniform("u_correction", ShaderUniform::Int(0));
+                        p.set_uniform("u_line_thickness", ShaderUniform::Float(render.line_thickness));
+                        p.set_uniform("u_falloff", ShaderUniform::Float(render.falloff));
+                    }
+                }
+                let (width, height) = target_size(view);
+                p.set_uniform("u_viewport", ShaderUniform::Float2([width as f32, height as f32]));
+                p.set_uniform("u_stroke", ShaderUniform::Float4(settings.strokes.visible.uniform(view.pixel_scale, view.time * settings.strokes.animation_speed)));
+                set_transform_uniforms(p, &model, &view_projection);
+                set_material_uniforms(p, render);
+                p.set_uniform("u_wireframe_color", ShaderUniform::Float4(render.line_color.into()));
+                p.flush();
+
+                helpers::gl_set_enabled(gl::SAMPLE_ALPHA_TO_COVERAGE, antialiasing.alpha_to_coverage);
+                scene.execute("single_pass", &*scene.command_lists.singlepass);
+                helpers::gl_set_enabled(gl::SAMPLE_ALPHA_TO_COVERAGE, false);
+            }
+            WireframeMode::MultiPass | WireframeMode::FeatureEdges if render.mode == WireframeMode::FeatureEdges || settings.lines.quads => {
+                let p = &scene.pipelines.default;
+                set_transform_uniforms(p, &model, &view_projection);
+                set_material_uniforms(p, render);
+                p.flush();
+                scene.execute("shading", &*scene.command_lists.default);
+
+                draw_quad_lines(scene, view, &model, &view_projection, render.mode == WireframeMode::FeatureEdges);
+            }
+            WireframeMode::MultiPass | WireframeMode::FeatureEdges => {
+                //#TODO: Rebuild command list if needed?
+                set_transform_uniforms(&scene.pipelines.default, &model, &view_projection);
+                set_material_uniforms(&scene.pipelines.default, render);
+                set_transform_uniforms(&scene.pipelines.wireframe, &model, &view_projection);
+
+                scene.pipelines.default.flush();
+                scene.pipelines.wireframe.flush();
+                gl::LineWidth(render.line_thickness * view.pixel_scale);
+                helpers::gl_set_enabled(gl::LINE_SMOOTH, antialiasing.line_smooth);
+                scene.execute("multi_pass", &*scene.command_lists.multipass);
+                helpers::gl_set_enabled(gl::LINE_SMOOTH, false);
+            }
+            WireframeMode::UvSpace => {
+                let uv_view = &settings.uv_view;
+                let uv_transform = tile_uv_transform(uv_view.uv_transform(view.width, view.height), view.tile);
+
+                let p = &scene.pipelines.uv_background;
+                p.set_uniform("u_uv_transform", ShaderUniform::Float4(uv_transform));
+                p.set_uniform("u_checker_count", ShaderUniform::Float(uv_view.checker_count));
+                match scene.uv_texture {
+                    Some(t) => {
+                        p.set_uniform("u_texture", ShaderUniform::Sampler2D(t.id));
+                        p.set_uniform("u_use_texture", ShaderUniform::Int(1));
+                    },
+                    None => p.set_uniform("u_use_texture", ShaderUniform::Int(0)),
+                }
+                p.flush();
+
+                // Reuses the corrected single pass edge distance for constant width lines
+                let p = &scene.pipelines.uv_wireframe;
+                p.set_uniform("u_uv_transform", ShaderUniform::Float4(uv_transform));
+                p.set_uniform("u_correction", ShaderUniform::Int(1));
+                p.set_uniform("u_line_thickness", ShaderUniform::Float(render.line_thickness * clip_scale));
+                p.set_uniform("u_falloff", ShaderUniform::Float(render.falloff * clip_scale));
+                p.set_uniform("u_wireframe_color", ShaderUniform::Float4(render.line_color));
+                p.set_uniform("u_fill_color", ShaderUniform::Float4(uv_view.fill_color));
+                p.set_uniform("u_out_of_range_color", ShaderUniform::Float4(uv_view.out_of_range_color));
+                p.flush();
+
+                scene.execute("uv", &*scene.command_lists.uv);
+            }
+            WireframeMode::Heatmap => {
+                let p = &scene.pipelines.heatmap;
+                set_transform_uniforms(p, &model, &view_projection);
+                p.set_uniform("u_correction", ShaderUniform::Int(0));
+                p.set_uniform("u_line_thickness", ShaderUniform::Float(render.line_thickness));
+                p.set_uniform("u_falloff", ShaderUniform::Float(render.falloff));
+                p.set_uniform("u_wireframe_color", ShaderUniform::Float4(render.line_color));
+                p.flush();
+
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, scene.gpu_mesh.metrics_buffer);
+                scene.execute("heatmap", &*scene.command_lists.heatmap);
+            }
+            WireframeMode::ScreenSpace => {
+                let gbuffer = match update_gbuffer(scene, view) {
+                    Some(gbuffer) => gbuffer,
+                    None => return,
+                };
+
+                // Normals and depth first, the outer pass is resumed afterwards
+                let mut begin_gbuffer = scene.gl.borrow().create_command_list();
+                begin_gbuffer.begin_render_pass(Some(&gbuffer), &RenderPassDesc::clear([0.0, 0.0, 0.0, 0.0]));
+                begin_gbuffer.execute(scene.gl);
+
+                let p = &scene.pipelines.gbuffer;
+                set_transform_uniforms(p, &model, &view_projection);
+                p.flush();
+                scene.execute("gbuffer", &*scene.command_lists.gbuffer);
+
+                let mut resume = scene.gl.borrow().create_command_list();
+                resume.end_render_pass();
+                resume.begin_render_pass(view.target.as_ref(), &RenderPassDesc::load());
+                if view.target.is_none() {
+                    resume.set_viewport(Rect::new(0, 0, view.width, view.height));
+                }
+                resume.set_scissor(view.scissor);
+                resume.execute(scene.gl);
+
+                let p = &scene.pipelines.default;
+                set_transform_uniforms(p, &model, &view_projection);
+                set_material_uniforms(p, render);
+                p.flush();
+                scene.execute("shading", &*scene.command_lists.default);
+
+                let edges = &settings.edge_detection;
+                let p = &scene.pipelines.edges;
+                p.set_uniform("u_gbuffer", ShaderUniform::Sampler2D(gbuffer.color_texture(0).unwrap_or(0)));
+                p.set_uniform("u_operator", ShaderUniform::Int(edges.operator.to_int()));
+                p.set_uniform("u_depth_threshold", ShaderUniform::Float(edges.depth_threshold));
+                p.set_uniform("u_normal_threshold", ShaderUniform::Float(edges.normal_threshold));
+                p.set_uniform("u_line_thickness", ShaderUniform::Float(edges.line_width * view.pixel_scale));
+                p.set_uniform("u_wireframe_color", ShaderUniform::Float4(render.line_color));
+                p.flush();
+                scene.execute("edge_filter", &*scene.command_lists.edges);
+            }
+        }
+
+        // Draw the vector overlay on top of the active mode, it has no meaning in uv space
+        let overlay = &settings.vector_overlay;
+        if overlay.enabled() && render.mode != WireframeMode::UvSpace {
+            let p = &scene.pipelines.vectors;
+            set_transform_uniforms(p, &model, &view_projection);
+            p.set_uniform("u_show_normals", ShaderUniform::Bool(overlay.show_normals));
+            p.set_uniform("u_show_tangents", ShaderUniform::Bool(overlay.show_tangents));
+            p.set_uniform("u_show_bitangents", ShaderUniform::Bool(overlay.show_bitangents));
+            p.set_uniform("u_vector_length", ShaderUniform::Float(overlay.length));
+            p.set_uniform("u_normal_color", ShaderUniform::Float4(overlay.normal_color));
+            p.set_uniform("u_tangent_color", ShaderUniform::Float4(overlay.tangent_color));
+            p.set_uniform("u_bitangent_color", ShaderUniform::Float4(overlay.bitangent_color));
+            p.flush();
+            scene.execute("vector_overlay", &*scene.command_lists.vectors);
+        }
+    }
+}
+
+/// Size in pixels of the target the view is rendered into
+fn target_size(view : &SceneView) -> (u32, u32) {
+    match &view.target {
+        Some(target) => (target.desc().width, target.desc().height),
+        None => (view.width, view.height),
+    }
+}
+
+/// Draws the edges of the mesh as screen-aligned quads on top of the current contents.
+/// Hidden edges are drawn first, behind the depth of the shaded pass.
+unsafe fn draw_quad_lines(scene : &Scene, view : &SceneView, model : &na::Mat4, view_projection : &na::Mat4, feature_edges_only : bool) {
+    let render = &scene.settings.render;
+    let lines = &scene.settings.lines;
+    let strokes = &scene.settings.strokes;
+    let (width, height) = target_size(view);
+    let phase = view.time * strokes.animation_speed;
+
+    let set_uniforms = |p : &Pipeline, stroke : [f32; 4], silhouette_stroke : [f32; 4]| {
+        set_transform_uniforms(p, model, view_projection);
+        p.set_uniform("u_eye", ShaderUniform::Float3(scene.settings.camera.eye));
+        p.set_uniform("u_viewport", ShaderUniform::Float2([width as f32, height as f32]));
+        p.set_uniform("u_line_width", ShaderUniform::Float(lines.width * view.pixel_scale));
+        p.set_uniform("u_cap", ShaderUniform::Int(lines.cap.to_int()));
+        p.set_uniform("u_smooth", ShaderUniform::Int(view.antialiased as i32));
+        p.set_uniform("u_feature_edges_only", ShaderUniform::Int(feature_edges_only as i32));
+        p.set_uniform("u_crease_angle", ShaderUniform::Float(lines.crease_angle));
+        p.set_uniform("u_color_by_category", ShaderUniform::Int(lines.color_by_category as i32));
+        p.set_uniform("u_wireframe_color", ShaderUniform::Float4(render.line_color));
+        p.set_uniform("u_boundary_color", ShaderUniform::Float4(lines.boundary_color));
+        p.set_uniform("u_crease_color", ShaderUniform::Float4(lines.crease_color));
+        p.set_uniform("u_stroke", ShaderUniform::Float4(stroke));
+        p.set_uniform("u_silhouette_stroke", ShaderUniform::Float4(silhouette_stroke));
+        p.flush();
+    };
+
+    gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, scene.gpu_mesh.edge_buffer);
+
+    if strokes.show_hidden {
+        let hidden = strokes.hidden.uniform(view.pixel_scale, phase);
+        set_uniforms(&scene.pipelines.lines_hidden, hidden, hidden);
+        scene.execute("hidden_lines", &*scene.command_lists.lines_hidden);
+    }
+
+    set_uniforms(
+        &scene.pipelines.lines,
+        strokes.visible.uniform(view.pixel_scale, phase),
+        strokes.silhouette.uniform(view.pixel_scale, phase),
+    );
+    scene.execute("lines", &*scene.command_lists.lines);
+}
+
+/// Number of G-buffer sizes kept around. Turntables and tiled captures render at another size
+/// than the window in the same frame, so a single G-buffer would be recreated twice per frame.
+const GBUFFER_SIZES : usize = 3;
+
+/// G-buffer of the screen-space mode matching the size of the view target, the least recently
+/// used one is dropped when a new size doesn't fit anymore
+fn update_gbuffer(scene : &Scene, view : &SceneView) -> Option<RenderTargetHandle> {
+    let (width, height) = target_size(view);
+
+    let mut gbuffers = scene.gbuffer.borrow_mut();
+    let gbuffer = match gbuffers.iter().position(|g| g.desc().width == width && g.desc().height == height) {
+        Some(index) => gbuffers.remove(index),
+        None => {
+            let desc = RenderTargetDesc::color_depth(width, height, device::Format::Rgba16F, device::Format::Depth24Stencil8).with_label("gbuffer");
+            match scene.gl.borrow().create_render_target(&desc) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("{}", e);
+                    return None;
+                }
+            }
+        }
+    };
+
+    gbuffers.push(gbuffer.clone());
+    if gbuffers.len() > GBUFFER_SIZES {
+        gbuffers.remove(0);
+    }
+    Some(gbuffer)
+}
+
+/// Renders the scene to the window, through the multisampled target when there is one.
+/// When comparing, the left half is rendered without any anti-aliasing.
+fn render_frame(scene : &Scene, view : &SceneView, msaa_target : Option<&RenderTargetHandle>) {
+    let size = (view.width, view.height);
+    let compare = scene.settings.antialiasing.compare;
+    let full = Rect::new(0, 0, view.width, view.height);
+    let antialiased_rect = if compare {
+        Rect::new((view.width / 2) as i32, 0, view.width - view.width / 2, view.height)
+    } else {
+        full
+    };
+
+    window_pass(scene.gl, size, &RenderPassDesc::clear([0.0, 0.0, 0.0, 1.0])).execute(scene.gl);
+    if compare {
+        render_scene(scene, &SceneView { antialiased: false, ..view.clone() });
+    }
+
+    match msaa_target {
+        Some(target) => {
+            let mut pass = scene.gl.borrow().create_command_list();
+            pass.begin_render_pass(Some(target), &RenderPassDesc::clear([0.0, 0.0, 0.0, 1.0]));
+            pass.execute(scene.gl);
+
+            render_scene(scene, &SceneView { target: Some(target.clone()), ..view.clone() });
+
+            let mut resolve = scene.gl.borrow().create_command_list();
+            resolve.end_render_pass();
+            resolve.resolve(target, None, antialiased_rect);
+            scene.execute("resolve", &*resolve);
+            window_pass(scene.gl, size, &RenderPassDesc::load()).execute(scene.gl);
+        },
+        None => {
+            let scissor_rect = if compare { Some(antialiased_rect) } else { None };
+            let mut scissor = scene.gl.borrow().create_command_list();
+            scissor.set_scissor(scissor_rect);
+            // The unsmoothed pass already wrote depth and color here
+            if compare {
+                scissor.clear([0.0, 0.0, 0.0, 1.0], Some(1.0));
+            }
+            scissor.execute(scene.gl);
+
+            render_scene(scene, &SceneView { scissor: scissor_rect, ..view.clone() });
+
+            let mut reset = scene.gl.borrow().create_command_list();
+            reset.set_scissor(None);
+            reset.execute(scene.gl);
+        }
+    }
+}
+
+/// Copies the filters of the settings to the debug log
+fn apply_debug_settings(log : &device::debug::DebugLogHandle, settings : &settings::DebugSettings) {
+    if let Ok(mut log) = log.lock() {
+        log.filter.min_severity = settings.min_severity;
+        log.filter.ignored_ids = settings.ignored_ids.clone();
+        log.filter.deduplicate = settings.deduplicate;
+        log.break_on_error = settings.break_on_error;
+    }
+}
+
+/// Renders the scene into an offscreen target, used by the benchmark so the results don't
+/// depend on the window being visible
+fn render_offscreen(scene : &Scene, view : &SceneView, target : &RenderTargetHandle) {
+    let mut pass = scene.gl.borrow().create_command_list();
+    pass.begin_render_pass(Some(target), &RenderPassDesc::clear([0.0, 0.0, 0.0, 1.0]));
+    pass.execute(scene.gl);
+
+    render_scene(scene, &SceneView { target: Some(target.clone()), ..view.clone() });
+
+    let mut end = scene.gl.borrow().create_command_list();
+    end.end_render_pass();
+    end.execute(scene.gl);
+}
+
+/// Multisampled target matching the window, recreated when the size or sample count changes
+fn update_msaa_target(gl : &DeviceHandle, target : &mut Option<RenderTargetHandle>, size : (u32, u32), samples : u32) {
+    let samples = samples.min(gl.borrow().max_sample_count());
+    if samples <= 1 {
+        *target = None;
+        return;
+    }
+
+    let matches = target.as_ref().map(|t| {
+        let desc = t.desc();
+        desc.width == size.0 && desc.height == size.1 && desc.sample_count == samples
+    }).unwrap_or(false);
+    if matches {
+        return;
+    }
+
+    let desc = RenderTargetDesc {
+        sample_count: samples,
+        ..RenderTargetDesc::color_depth(size.0, size.1, device::Format::Rgba8, device::Format::Depth24Stencil8).with_label("msaa")
+    };
+    *target = match gl.borrow().create_render_target(&desc) {
+        Ok(t) => Some(t),
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    };
+}
+
+/// Command list that starts rendering to the window with a viewport covering it
+fn window_pass(gl : &DeviceHandle, size : (u32, u32), desc : &RenderPassDesc) -> Box<dyn CommandList> {
+    let mut list = gl.borrow().create_command_list();
+    list.begin_render_pass(None, desc);
+    list.set_viewport(Rect::new(0, 0, size.0, size.1));
+    list
+}
+
+/// Writes the scene to a PNG at `scale` times the view size. At 1x the default framebuffer is read back as is,
+/// unless the window is hidden: its back buffer is undefined then and the scene is rendered offscreen instead.
+fn save_capture(path : &std::path::Path, scale : u32, window_visible : bool, scene : &Scene, view : &SceneView) -> Result<(), String> {
+    if scale <= 1 && window_visible {
+        return capture::save_framebuffer(path, view.width, view.height);
+    }
+
+    let scale = scale.max(1);
+    let (width, height) = (view.width * scale, view.height * scale);
+    let pixels = scene.profile("capture", || capture::render_tiled(scene.gl, width, height, scene.settings.antialiasing.msaa_samples, &mut |tile, target| {
+        render_scene(scene, &SceneView {
+            tile: tile.ndc_transform(),
+            pixel_scale: scale as f32,
+            target: Some(target.clone()),
+            ..view.clone()
+        });
+    }))?;
+    capture::write_png(path, width, height, &pixels)
+}
+
+/// Saves a screenshot with the screenshot settings
+fn take_screenshot(scene : &Scene, view : &SceneView) {
+    let screenshot = &scene.settings.screenshot;
+    let directory = std::path::Path::new(&screenshot.directory);
+    if let Err(e) = std::fs::create_dir_all(directory) {
+        println!("Failed to create \"{}\": {}", directory.display(), e);
+        return;
+    }
+
+    let path = capture::screenshot_path(directory);
+    match save_capture(&path, screenshot.scale, true, scene, view) {
+        Ok(_) => println!("Saved screenshot \"{}\"", path.display()),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Renders the next frame of the turntable at the recording resolution and hands it to the recorder
+fn record_turntable_frame(recorder : &mut turntable::Recorder, scene : &Scene, window_size : (u32, u32)) -> Result<(), String> {
+    let (width, height) = (recorder.width, recorder.height);
+    let pixels = {
+        let scene = Scene {
+            settings: &recorder.settings,
+            ..*scene
+        };
+        let view = SceneView {
+            model: na::rotation(recorder.elapsed(), &na::Vec3::new(0.0, 1.0, 0.0)),
+            view_projection: recorder.settings.camera.view_projection(width, height),
+            width,
+            height,
+            tile: FULL_TILE,
+            // Keep the pixel sized lines in proportion with the window
+            pixel_scale: height as f32 / window_size.1.max(1) as f32,
+            antialiased: true,
+            target: None,
+            scissor: None,
+            time: recorder.elapsed(),
+        };
+        scene.profile("turntable", || capture::render_tiled(scene.gl, width, height, recorder.settings.antialiasing.msaa_samples, &mut |tile, target| {
+            render_scene(&scene, &SceneView { tile: tile.ndc_transform(), target: Some(target.clone()), ..view.clone() });
+        }))?
+    };
+    recorder.add_frame(pixels)
+}
+
+/// Uploads the object transform, the inverse transpose is used to transform normals
+fn set_transform_uniforms(pipeline : &Pipeline, model : &na::Mat4, view_projection : &na::Mat4) {
+    let normal_matrix = na::transpose(&na::inverse(&na::mat4_to_mat3(model)));
+    pipeline.set_uniform("model", ShaderUniform::Mat4((*model).into()));
+    pipeline.set_uniform("view_projection", ShaderUniform::Mat4((*view_projection).into()));
+    pipeline.set_uniform("normal_matrix", ShaderUniform::Mat3(normal_matrix.into()));
+}
+
+/// Uploads the solid color properties used by the shaded pipelines
+fn set_material_uniforms(pipeline : &Pipeline, render : &settings::RenderSettings) {
+    pipeline.set_uniform("u_object_color", ShaderUniform::Float4(render.solid_color.into()));
+    pipeline.set_uniform("u_color_source", ShaderUniform::Int(render.color_source.to_int()));
+}
+
+/// Loads the texture for the uv view, an empty path means the checkerboard is used
+fn load_uv_texture(path : &str) -> Option<texture::Texture> {
+    if path.is_empty() {
+        return None;
+    }
+
+    match texture::Texture::load_png(std::path::Path::new(path)) {
+        Ok(t) => {
+            helpers::object_label(gl::TEXTURE, t.id, &format!("uv texture {}", path));
+            Some(t)
+        },
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    }
+}
+
+/// Writes the edges of the mesh as seen by the camera to an SVG or PDF file
+fn export_line_art(path : &std::path::Path, mesh : &mesh::MeshData, model_view_projection : &na::Mat4, size : (u32, u32), settings : &Settings) -> Result<(), String> {
+    let export = &settings.export;
+    let segments = vector_export::project_edges(&mesh.vertices, &mesh.indices, model_view_projection, size.0, size.1, export.hidden_line_removal);
+    let origin = model_view_projection * na::vec4(0.0, 0.0, 0.0, 1.0);
+    let style = vector_export::Style {
+        stroke_width: export.stroke_width(&settings.render, &settings.lines, &settings.edge_detection, size.1, origin.w),
+        color: settings.render.line_color,
+        background: export.background_color,
+    };
+    vector_export::write(path, size.0, size.1, &segments, &style)?;
+    println!("Exported {} lines to \"{}\"", segments.len(), path.display());
+    Ok(())
+}
+
+/// Label shown in the mesh list, the file name without its directory
+fn mesh_label(path : &str) -> imgui::ImString {
+    let name = std::path::Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+    imgui::ImString::new(name)
 }
 
 fn main() {
+    let options = match cli::Options::from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if options.list_modes {
+        cli::print_modes();
+        return;
+    }
+
+    // Load the render configuration, command-line options take precedence
+    let config_path = options.config_path();
+    let mut settings = Settings::load_or_default(&config_path);
+    options.apply(&mut settings);
+
+    if let Some(dir) = &options.bake {
+        match mesh_cache::bake_directory(dir, &settings.processing) {
+            Ok(count) => println!("Baked {} mesh cache(s)", count),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let mut preset_name = imgui::ImString::with_capacity(64);
+    preset_name.push_str("My preset");
+    let mut screenshot_directory = imgui::ImString::with_capacity(256);
+    screenshot_directory.push_str(&settings.screenshot.directory);
+    let mut turntable_output = imgui::ImString::with_capacity(256);
+    turntable_output.push_str(&settings.turntable.output);
+    let mut export_path = imgui::ImString::with_capacity(256);
+    export_path.push_str(&settings.export.path);
+    let mut profile_csv_path = imgui::ImString::with_capacity(256);
+    profile_csv_path.push_str(&settings.profiler.csv_path);
+
     // Setup SDL2
     let sdl = sdl2::init().unwrap();
     let video_subsystem = sdl.video().unwrap();
@@ -52,23 +928,30 @@ fn main() {
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(4, 5);
 
-    let window = video_subsystem
-        .window("Wireframe Techniques", 800, 600)
-        .opengl()
-        .position_centered()
-        .resizable()
-        .build()
-        .unwrap();
+    let mut window_builder = video_subsystem.window("Wireframe Techniques", settings.window.width, settings.window.height);
+    window_builder.opengl().position_centered().resizable();
+    if options.headless() {
+        window_builder.hidden();
+    }
+    let window = window_builder.build().unwrap();
 
     // Create the GL device
     let mut gl = device::create_default_device(&window);
 
-    // Enable opengl callbacks
-    gl.borrow().enable_debug_layer().expect("Failed to enable debugging capabilities!");
+    // Route the opengl debug output into the log shown by the console
+    let debug_log = device::debug::DebugLog::shared(500);
+    apply_debug_settings(&debug_log, &settings.debug);
+    // Headless runs have no console to show the messages in
+    if let Ok(mut log) = debug_log.lock() {
+        log.echo = options.headless();
+    }
+    let mut debug_synchronous = settings.debug.synchronous_output();
+    gl.borrow_mut().enable_debug_layer(&debug_log, debug_synchronous).expect("Failed to enable debugging capabilities!");
+    let mut debug_console = crate::ui::debug_console::DebugConsole::new();
 
-    // Disable vsync
+    // Enable or disable vsync
     video_subsystem
-        .gl_set_swap_interval(1)
+        .gl_set_swap_interval(if settings.window.vsync { 1 } else { 0 })
         .expect("Failed to set swap interval.");
 
 
@@ -78,78 +961,146 @@ fn main() {
 
     // Build some shaders
     let shader_building = PreciseTime::now();
-    let mut draw_mode = WireframeMode::None;
     let mut paused = false;
 
     // Create the default shader programs
     let default_vert = include_bytes!("../shaders/default.vert");
     let default_frag = include_bytes!("../shaders/default.frag");
 
-    let default_program = Arc::new(Pipeline::create_simple(default_vert, default_frag).unwrap());
+    let default_program = Arc::new({
+        let mut p = Pipeline::create_simple(default_vert, default_frag).unwrap();
+        p.set_label("default");
+        p
+    });
     let wireframe_program = Arc::new({
         let mut p = Pipeline::create_simple(default_vert, include_bytes!("../shaders/wireframe.frag")).expect("Failed to create the wireframe program.");
+        p.set_label("wireframe");
         p.set_fill_mode(crate::pipeline::FillMode::Lines);
         p.set_depth_test(false);
+        // Smoothed lines blend their coverage
+        p.set_blending(true);
+        p
+    });
+    let wireframe_singlepass = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(default_vert, include_bytes!("../shaders/default.geom"), include_bytes!("../shaders/default_wireframe.frag")).expect("Failed to create singlepass wireframe");
+        p.set_label("single pass wireframe");
         p
     });
-    let wireframe_singlepass = Arc::new(Pipeline::create_simple_with_geom(default_vert, include_bytes!("../shaders/default.geom"), include_bytes!("../shaders/default_wireframe.frag")).expect("Failed to create singlepass wireframe"));
 
     println!(
         "Shader compiling and setup took {}ms",
         shader_building.to(PreciseTime::now()).num_milliseconds()
     );
 
-    // Load our mesh and setup buffers
+    let vectors_program = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(include_bytes!("../shaders/vectors.vert"), include_bytes!("../shaders/vectors.geom"), include_bytes!("../shaders/vectors.frag")).expect("Failed to create the debug vectors program.");
+        p.set_label("vectors");
+        p.set_primitive_topology(device::PrimitiveTopology::Points);
+        p
+    });
+
+    let uv_background_program = Arc::new({
+        let mut p = Pipeline::create_simple(include_bytes!("../shaders/uv_background.vert"), include_bytes!("../shaders/uv_background.frag")).expect("Failed to create the uv background program.");
+        p.set_label("uv background");
+        p.set_depth_test(false);
+        p
+    });
+    let uv_wireframe_program = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(include_bytes!("../shaders/uv.vert"), include_bytes!("../shaders/default.geom"), include_bytes!("../shaders/uv_wireframe.frag")).expect("Failed to create the uv wireframe program.");
+        p.set_label("uv wireframe");
+        p.set_depth_test(false);
+        p.set_blending(true);
+        p
+    });
 
-    let mesh_process_start = PreciseTime::now();
+    let heatmap_program = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(default_vert, include_bytes!("../shaders/default.geom"), include_bytes!("../shaders/heatmap.frag")).expect("Failed to create the heatmap program.");
+        p.set_label("heatmap");
+        p
+    });
 
-    let (vertices,indices) = load_mesh("assets/suzanne.obj").expect("Failed to load model from disk!");
-    let mut active_mesh_index = 0;
-    let mesh_list = [
-        "assets/suzanne.obj",
-        "assets/cube.obj",
-    ];
-    let mesh_labels = vec![
-        imgui::im_str!("Suzanne"),
-        imgui::im_str!("Cube")
-    ];
+    let gbuffer_program = Arc::new({
+        let mut p = Pipeline::create_simple(default_vert, include_bytes!("../shaders/gbuffer.frag")).expect("Failed to create the G-buffer program.");
+        p.set_label("gbuffer");
+        p
+    });
+    let edges_program = Arc::new({
+        let mut p = Pipeline::create_simple(include_bytes!("../shaders/fullscreen.vert"), include_bytes!("../shaders/edges.frag")).expect("Failed to create the edge detection program.");
+        p.set_label("edge detection");
+        p.set_depth_test(false);
+        p.set_blending(true);
+        p
+    });
+
+    let lines_program = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(default_vert, include_bytes!("../shaders/lines.geom"), include_bytes!("../shaders/lines.frag")).expect("Failed to create the line program.");
+        p.set_label("lines");
+        p.set_primitive_topology(device::PrimitiveTopology::Lines);
+        p.set_blending(true);
+        p
+    });
+    // Same lines, only where they are behind the shaded mesh
+    let lines_hidden_program = Arc::new({
+        let mut p = Pipeline::create_simple_with_geom(default_vert, include_bytes!("../shaders/lines.geom"), include_bytes!("../shaders/lines.frag")).expect("Failed to create the hidden line program.");
+        p.set_label("hidden lines");
+        p.set_primitive_topology(device::PrimitiveTopology::Lines);
+        p.set_blending(true);
+        p.set_depth_compare(crate::pipeline::DepthCompare::Greater);
+        p.set_depth_write(false);
+        p
+    });
 
-    let mesh_process_end = PreciseTime::now();
+    let pipelines = Pipelines {
+        default: default_program,
+        wireframe: wireframe_program,
+        singlepass: wireframe_singlepass,
+        vectors: vectors_program,
+        uv_background: uv_background_program,
+        uv_wireframe: uv_wireframe_program,
+        heatmap: heatmap_program,
+        gbuffer: gbuffer_program,
+        edges: edges_program,
+        lines: lines_program,
+        lines_hidden: lines_hidden_program,
+    };
+
+    // Texture shown behind the uv layout
+    let mut uv_texture = load_uv_texture(&settings.uv_view.texture);
+    let mut uv_texture_path = imgui::ImString::with_capacity(256);
+    uv_texture_path.push_str(&settings.uv_view.texture);
+
+    // Load our mesh and setup buffers
+    // The CPU side data is kept around for the vector export
+    let mut mesh_data = mesh::load_mesh(&settings.mesh, &settings.processing).expect("Failed to load model from disk!");
     println!(
         "Loading and processing the mesh took {}ms",
-        mesh_process_start.to(mesh_process_end).num_milliseconds()
+        mesh_data.stats.load_time_ms
     );
 
-    // Construct our setup
-    let mut suzanne_vertex_buffer = 0;
-    let mut suzanne_index_buffer = 0;
-    unsafe {
-        gl::GenBuffers(1, &mut suzanne_vertex_buffer);
-        gl::BindBuffer(gl::ARRAY_BUFFER, suzanne_vertex_buffer);
-        let buffer_size = vertices.len() * std::mem::size_of::<GlVert>();
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            buffer_size as isize,
-            vertices.as_ptr() as *const std::ffi::c_void,
-            gl::STATIC_DRAW,
-        );
-
-        gl::GenBuffers(1, &mut suzanne_index_buffer);
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, suzanne_index_buffer);
-        let buffer_size = indices.len() * std::mem::size_of::<u32>();
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            buffer_size as isize,
-            indices.as_ptr() as *const std::ffi::c_void,
-            gl::STATIC_DRAW,
-        );
-    }
-    let suzanne_vao = unsafe { GlVert::setup_vao(suzanne_vertex_buffer) };
-
-    let duration = mesh_process_end.to(PreciseTime::now());
+    let mut mesh_list = vec![
+        "assets/suzanne.obj".to_string(),
+        "assets/cube.obj".to_string(),
+    ];
+    let mut mesh_labels = vec![
+        imgui::ImString::new("Suzanne"),
+        imgui::ImString::new("Cube")
+    ];
+    for mesh in options.meshes.iter().chain(std::iter::once(&settings.mesh)).chain(settings.recent_files.iter()) {
+        if !mesh_list.contains(mesh) {
+            mesh_list.push(mesh.clone());
+            mesh_labels.push(mesh_label(mesh));
+        }
+    }
+    let mut active_mesh_index = mesh_list.iter().position(|m| *m == settings.mesh).unwrap_or(0) as i32;
+    let mut file_browser = ui::file_browser::FileBrowser::new(&std::env::current_dir().unwrap_or_default());
+    let mut mesh_loader = mesh_loader::MeshLoader::new();
+
+    let gpu_upload_start = PreciseTime::now();
+    let mut gpu_mesh = GpuMesh::new(&mesh_data);
+    gpu_mesh.upload_metrics(&mesh_data.analysis.heatmap_values(settings.render.heatmap_metric));
     println!(
         "Submitting mesh data to GPU took {}ms",
-        duration.num_milliseconds()
+        gpu_upload_start.to(PreciseTime::now()).num_milliseconds()
     );
 
     let mut event_pump = sdl.event_pump().unwrap();
@@ -162,59 +1113,52 @@ fn main() {
         gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
     }
 
-    let window_size = window.size();
-    let aspect = window_size.0 as f32 / window_size.1 as f32;
-
-    // Define some default matrices
-    let view = na::look_at(
-        &na::Vec3::new(4.0, 1.8, 4.0),
-        &na::Vec3::new(0.0, 0.0, 0.0),
-        &na::Vec3::new(0.0, 1.0, 0.0),
-    );
-
     // Record the start timings
     let _start_time = time::precise_time_s();
     let mut elapsed = 0.0;
     let mut curr_time = 0.0;
-    let mut curr_item = 0;
-
-    // Properties
-    let mut line_thickness = 0.01;
-
-    let mut line_color = [0.0,0.0,0.0,1.0];
-    let mut solid_color = [1.0,1.0,1.0,1.0];
+    let mut curr_item = settings.render.mode.to_int() as i32;
+    let mut curr_preset = 0;
+    let mut frame_index = 0;
 
     // Create some command lists.
-    let clear_color = [0.3,0.3,0.3,1.0];
-    let mut default_list = gl.borrow().create_command_list();
-    {
-        default_list.clear(clear_color, None);
-        default_list.bind_pipeline(&default_program);
-        default_list.bind_vertex_buffers(0, 1, &[suzanne_vertex_buffer], &[0]);
-        default_list.bind_index_buffer(&suzanne_index_buffer, 0, device::IndexType::UnsignedInt);
-        default_list.draw_indexed(indices.len() as u32, 1, 0,0,0);
-    }
-
-    let mut singlepass_list = gl.borrow().create_command_list();
-    {
-        singlepass_list.clear(clear_color, None);
-        singlepass_list.bind_pipeline(&wireframe_singlepass);
-        singlepass_list.bind_vertex_buffers(0, 1, &[suzanne_vertex_buffer], &[0]);
-        singlepass_list.bind_index_buffer(&suzanne_index_buffer, 0, device::IndexType::UnsignedInt);
-        singlepass_list.draw_indexed(indices.len() as u32, 1, 0,0,0);
-    }
-
-    let mut multipass_list = gl.borrow().create_command_list();
-    {
-        multipass_list.clear(clear_color, None);
-        multipass_list.bind_pipeline(&default_program);
-        multipass_list.bind_vertex_buffers(0, 1, &[suzanne_vertex_buffer], &[0]);
-        multipass_list.bind_index_buffer(&suzanne_index_buffer, 0, device::IndexType::UnsignedInt);
-        multipass_list.draw_indexed(indices.len() as u32, 1, 0,0,0);
-
-        multipass_list.bind_pipeline(&wireframe_program);
-        multipass_list.draw_indexed(indices.len() as u32, 1, 0,0,0);
-    }
+    let mut command_lists = record_command_lists(&gl, &pipelines, &gpu_mesh);
+
+    // Scene target when MSAA is enabled
+    let mut msaa_target : Option<RenderTargetHandle> = None;
+    let gbuffer : RefCell<Vec<RenderTargetHandle>> = RefCell::new(Vec::new());
+    let profiler = RefCell::new(profiler::Profiler::new(settings.profiler.history_length));
+
+    // The benchmark reads its GPU times from the profiler
+    let benchmark_size = (settings.window.width, settings.window.height);
+    let mut benchmark = if options.benchmark {
+        settings.profiler.enabled = true;
+        settings.profiler.history_length = settings.profiler.history_length.max(64);
+        let config = benchmark::BenchmarkConfig {
+            meshes: options.benchmark_meshes(&settings),
+            modes: WireframeMode::ALL.to_vec(),
+            warmup_frames: options.benchmark_warmup,
+            measured_frames: options.benchmark_frames,
+            output: options.benchmark_output.clone(),
+        };
+        match benchmark::Benchmark::new(config, &gl, benchmark_size, settings.antialiasing.msaa_samples) {
+            Ok(b) => Some(b),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Recording started from the UI or the command line
+    let mut turntable_recorder = options.turntable.as_ref().map(|output| {
+        settings.turntable.output = output.to_string_lossy().into_owned();
+        settings.turntable.format = turntable::TurntableFormat::from_path(output);
+        settings.turntable.mode = settings.render.mode;
+        turntable::Recorder::new(&settings, (settings.window.width, settings.window.height))
+    });
 
     // Run the application
     'app: loop {
@@ -226,6 +1170,20 @@ fn main() {
             elapsed += dt;
         }
 
+        {
+            let mut profiler = profiler.borrow_mut();
+            profiler.enabled = settings.profiler.enabled;
+            profiler.history_length = settings.profiler.history_length.max(1);
+            profiler.begin_frame();
+        }
+
+        // File the user asked to open this frame (drag-and-drop or the file browser)
+        let mut open_request : Option<String> = None;
+        // Line art export requested from the UI, written once the camera matrices are known
+        let mut export_request = false;
+        let mut screenshot_request = false;
+        let mut record_turntable = false;
+
         for e in event_pump.poll_iter() {
             use sdl2::event::Event;
             use sdl2::keyboard::Keycode;
@@ -241,6 +1199,11 @@ fn main() {
                     keycode: Some(Keycode::P),
                     ..
                 } => paused = !paused,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => screenshot_request = true,
+                Event::DropFile { filename, .. } => open_request = Some(filename),
                 _ => {}
             }
         }
@@ -257,123 +1220,567 @@ fn main() {
             use imgui::ImGuiCond;
 
             let prv_mesh = active_mesh_index;
+            let labels : Vec<&imgui::ImStr> = mesh_labels.iter().map(|l| l.as_ref()).collect();
+            let preset_names = settings.preset_names();
+            let preset_labels : Vec<imgui::ImString> = preset_names.iter().map(|n| imgui::ImString::new(n.clone())).collect();
+            let preset_label_refs : Vec<&imgui::ImStr> = preset_labels.iter().map(|l| l.as_ref()).collect();
+            let render = &mut settings.render;
+            let processing = &mut settings.processing;
+            let vector_overlay = &mut settings.vector_overlay;
+            let uv_view = &mut settings.uv_view;
+            let export = &mut settings.export;
+            let screenshot = &mut settings.screenshot;
+            let antialiasing = &mut settings.antialiasing;
+            let edge_detection = &mut settings.edge_detection;
+            let lines = &mut settings.lines;
+            let strokes = &mut settings.strokes;
+            let profiler_settings = &mut settings.profiler;
+            let debug_settings = &mut settings.debug;
+            let mut export_profile = false;
+            let turntable = &mut settings.turntable;
+            let mut load_texture = false;
+            let mut heatmap_changed = false;
+            let mut reload_mesh = false;
+            let mut load_preset = false;
+            let mut save_preset = false;
+            let mut save_settings = false;
             ui.window(im_str!("Wireframe-rs"))
                 .size((300.0, 100.0), ImGuiCond::FirstUseEver)
                 .build(|| {
-                    ui.combo(im_str!("Mesh"), &mut active_mesh_index, &mesh_labels,10);
+                    ui.combo(im_str!("Mesh"), &mut active_mesh_index, &labels,10);
+                    if let Some(pending) = mesh_loader.pending() {
+                        let overlay = imgui::ImString::new(format!("{} {}", pending.stage.name(), pending.path));
+                        ui.progress_bar(pending.progress).overlay_text(&overlay).build();
+                    }
 
-                    ui.combo(im_str!("Draw mode"), &mut curr_item, &[im_str!("Default"), im_str!("Singlepass"), im_str!("Singlepass correction"), im_str!("Multipass")], 10);
-                    ui.slider_float(im_str!("Line thickness"), &mut line_thickness, 0.001, 1.0).build();
+                    if ui.collapsing_header(im_str!("Open file")).build() {
+                        if let Some(path) = file_browser.draw(&ui) {
+                            open_request = Some(path.to_string_lossy().into_owned());
+                        }
+                    }
 
+                    if ui.collapsing_header(im_str!("Mesh statistics")).build() {
+                        crate::ui::import_stats(&ui, &mesh_data.stats);
+                    }
+
+                    if ui.collapsing_header(im_str!("Mesh quality")).build() {
+                        crate::ui::mesh_analysis(&ui, &mesh_data.analysis);
+                    }
+
+                    if ui.collapsing_header(im_str!("Mesh processing")).build() {
+                        let mut normals = processing.normals.to_int();
+                        ui.combo(im_str!("Normals"), &mut normals, &[im_str!("Import"), im_str!("Smooth"), im_str!("Flat")], 10);
+                        processing.normals = mesh_processing::NormalMode::from_int(normals);
+                        ui.slider_float(im_str!("Smoothing angle"), &mut processing.smoothing_angle, 0.0, 180.0).build();
+                        ui.checkbox(im_str!("Generate tangents"), &mut processing.generate_tangents);
+                        ui.separator();
+                        ui.checkbox(im_str!("Weld vertices"), &mut processing.weld);
+                        ui.input_float(im_str!("Position tolerance"), &mut processing.weld_position_tolerance).build();
+                        ui.slider_float(im_str!("Normal angle"), &mut processing.weld_normal_angle, 0.0, 180.0).build();
+                        ui.input_float(im_str!("UV tolerance"), &mut processing.weld_uv_tolerance).build();
+                        ui.checkbox(im_str!("Optimize vertex cache"), &mut processing.optimize_vertex_cache);
+                        ui.checkbox(im_str!("Use mesh cache"), &mut processing.use_cache);
+                        reload_mesh = ui.small_button(im_str!("Reload"));
+                    }
+
+                    let mode_labels : Vec<&imgui::ImStr> = WireframeMode::ALL.iter().map(|m| m.label()).collect();
+                    ui.combo(im_str!("Draw mode"), &mut curr_item, &mode_labels, 10);
+                    ui.slider_float(im_str!("Line thickness"), &mut render.line_thickness, 0.001, 1.0).build();
+                    ui.slider_float(im_str!("Falloff"), &mut render.falloff, 0.0, 0.05).build();
+
+
+                    let mut color_source = render.color_source.to_int();
+                    ui.combo(im_str!("Color source"), &mut color_source, &[im_str!("Solid color"), im_str!("Vertex color"), im_str!("UV set 0"), im_str!("UV set 1"), im_str!("Normal")], 10);
+                    render.color_source = settings::ColorSource::from_int(color_source);
+
+                    ui.color_edit(im_str!("Solid color"), &mut render.solid_color ).build();
+                    ui.color_edit(im_str!("Wireframe color"), &mut render.line_color ).build();
+
+                    if WireframeMode::from_int(curr_item as u32) == WireframeMode::Heatmap {
+                        let mut metric = render.heatmap_metric.to_int();
+                        if ui.combo(im_str!("Heatmap metric"), &mut metric, &[im_str!("Aspect ratio"), im_str!("Min angle"), im_str!("Area"), im_str!("Problems")], 10) {
+                            render.heatmap_metric = mesh_analysis::HeatmapMetric::from_int(metric);
+                            heatmap_changed = true;
+                        }
+                    }
+
+                    if WireframeMode::from_int(curr_item as u32) == WireframeMode::UvSpace && ui.collapsing_header(im_str!("UV view")).build() {
+                        ui.input_text(im_str!("Texture (PNG)"), &mut uv_texture_path).build();
+                        load_texture = ui.small_button(im_str!("Load texture"));
+                        if let Some(texture) = &uv_texture {
+                            ui.text(format!("Loaded texture: {}x{}", texture.width, texture.height));
+                        }
+                        ui.slider_float(im_str!("Checker count"), &mut uv_view.checker_count, 1.0, 64.0).build();
+                        ui.slider_float(im_str!("Zoom"), &mut uv_view.zoom, 0.1, 2.0).build();
+                        ui.color_edit(im_str!("Fill color"), &mut uv_view.fill_color).build();
+                        ui.color_edit(im_str!("Out of range color"), &mut uv_view.out_of_range_color).build();
+                    }
+
+                    if WireframeMode::from_int(curr_item as u32) == WireframeMode::ScreenSpace && ui.collapsing_header(im_str!("Edge detection")).build() {
+                        let mut operator = edge_detection.operator.to_int();
+                        ui.combo(im_str!("Operator"), &mut operator, &[im_str!("Sobel"), im_str!("Roberts cross")], 10);
+                        edge_detection.operator = settings::EdgeOperator::from_int(operator);
+                        ui.slider_float(im_str!("Depth threshold"), &mut edge_detection.depth_threshold, 0.0, 1.0).build();
+                        ui.slider_float(im_str!("Normal threshold"), &mut edge_detection.normal_threshold, 0.0, 2.0).build();
+                        ui.slider_float(im_str!("Line width (px)"), &mut edge_detection.line_width, 1.0, 8.0).build();
+                    }
+
+                    let line_mode = WireframeMode::from_int(curr_item as u32);
+                    if (line_mode == WireframeMode::MultiPass || line_mode == WireframeMode::FeatureEdges) && ui.collapsing_header(im_str!("Lines")).build() {
+                        if line_mode == WireframeMode::MultiPass {
+                            ui.checkbox(im_str!("Quad lines"), &mut lines.quads);
+                        }
+                        ui.slider_float(im_str!("Width (pixels)"), &mut lines.width, 0.5, 16.0).build();
+                        let mut cap = lines.cap.to_int();
+                        ui.combo(im_str!("Caps"), &mut cap, &[im_str!("Butt"), im_str!("Square"), im_str!("Round")], 10);
+                        lines.cap = settings::LineCap::from_int(cap);
+                        ui.slider_float(im_str!("Crease angle"), &mut lines.crease_angle, 0.0, 180.0).build();
+                        ui.checkbox(im_str!("Color by category"), &mut lines.color_by_category);
+                        if lines.color_by_category {
+                            ui.color_edit(im_str!("Boundary color"), &mut lines.boundary_color).build();
+                            ui.color_edit(im_str!("Crease color"), &mut lines.crease_color).build();
+                        }
+                    }
+
+                    let quad_lines = line_mode == WireframeMode::FeatureEdges || (line_mode == WireframeMode::MultiPass && lines.quads);
+                    let stroke_mode = quad_lines || line_mode == WireframeMode::SinglePass || line_mode == WireframeMode::SinglePassCorrection;
+                    if stroke_mode && ui.collapsing_header(im_str!("Strokes")).build() {
+                        ui.text("Visible");
+                        crate::ui::stroke_style(&ui, "visible", &mut strokes.visible);
+                        if quad_lines {
+                            ui.text("Silhouette");
+                            crate::ui::stroke_style(&ui, "silhouette", &mut strokes.silhouette);
+                            ui.checkbox(im_str!("Show hidden lines"), &mut strokes.show_hidden);
+                            if strokes.show_hidden {
+                                crate::ui::stroke_style(&ui, "hidden", &mut strokes.hidden);
+                            }
+                        }
+                        ui.slider_float(im_str!("Animation speed"), &mut strokes.animation_speed, -64.0, 64.0).build();
+                    }
 
-                    ui.color_edit(im_str!("Solid color"), &mut solid_color ).build();
-                    ui.color_edit(im_str!("Wireframe color"), &mut line_color ).build();
+                    if ui.collapsing_header(im_str!("Anti-aliasing")).build() {
+                        let sample_counts = [1, 2, 4, 8];
+                        let mut msaa = sample_counts.iter().position(|s| *s == antialiasing.msaa_samples).unwrap_or(0) as i32;
+                        ui.combo(im_str!("MSAA"), &mut msaa, &[im_str!("Off"), im_str!("2x"), im_str!("4x"), im_str!("8x")], 10);
+                        antialiasing.msaa_samples = sample_counts[msaa as usize];
+                        ui.checkbox(im_str!("Alpha to coverage (single pass)"), &mut antialiasing.alpha_to_coverage);
+                        ui.checkbox(im_str!("Line smoothing (multi pass)"), &mut antialiasing.line_smooth);
+                        ui.checkbox(im_str!("Compare side-by-side"), &mut antialiasing.compare);
+                        if antialiasing.compare {
+                            ui.text("Left: no anti-aliasing, right: the options above");
+                        }
+                    }
+
+                    if ui.collapsing_header(im_str!("Vector overlay")).build() {
+                        ui.checkbox(im_str!("Show normals"), &mut vector_overlay.show_normals);
+                        ui.same_line(0.0);
+                        ui.checkbox(im_str!("Show tangents"), &mut vector_overlay.show_tangents);
+                        ui.same_line(0.0);
+                        ui.checkbox(im_str!("Show bitangents"), &mut vector_overlay.show_bitangents);
+                        ui.slider_float(im_str!("Vector length"), &mut vector_overlay.length, 0.001, 1.0).build();
+                        ui.color_edit(im_str!("Normal color"), &mut vector_overlay.normal_color).build();
+                        ui.color_edit(im_str!("Tangent color"), &mut vector_overlay.tangent_color).build();
+                        ui.color_edit(im_str!("Bitangent color"), &mut vector_overlay.bitangent_color).build();
+                    }
+
+                    if ui.collapsing_header(im_str!("Screenshot")).build() {
+                        ui.input_text(im_str!("Directory"), &mut screenshot_directory).build();
+                        let mut scale = screenshot.scale as i32;
+                        ui.slider_int(im_str!("Resolution scale"), &mut scale, 1, 8).build();
+                        screenshot.scale = scale.max(1) as u32;
+                        if screenshot.scale == 1 {
+                            ui.checkbox(im_str!("Include UI"), &mut screenshot.include_ui);
+                        }
+                        if ui.small_button(im_str!("Take screenshot (F12)")) {
+                            screenshot_request = true;
+                        }
+                        screenshot.directory = screenshot_directory.to_str().to_string();
+                    }
+
+                    if ui.collapsing_header(im_str!("Turntable")).build() {
+                        ui.input_text(im_str!("Output"), &mut turntable_output).build();
+                        let mut format = turntable.format.to_int();
+                        ui.combo(im_str!("Format"), &mut format, &[im_str!("PNG sequence"), im_str!("GIF"), im_str!("APNG")], 10);
+                        turntable.format = turntable::TurntableFormat::from_int(format);
+                        let mut turntable_mode = turntable.mode.to_int() as i32;
+                        ui.combo(im_str!("Turntable mode"), &mut turntable_mode, &mode_labels, 10);
+                        turntable.mode = WireframeMode::from_int(turntable_mode as u32);
+
+                        let mut frames = turntable.frames as i32;
+                        ui.slider_int(im_str!("Frames"), &mut frames, 1, 360).build();
+                        turntable.frames = frames.max(1) as u32;
+                        let mut fps = turntable.fps as i32;
+                        ui.slider_int(im_str!("FPS"), &mut fps, 1, 60).build();
+                        turntable.fps = fps.max(1) as u32;
+                        let mut resolution = [turntable.width as i32, turntable.height as i32];
+                        ui.input_int2(im_str!("Resolution (0: window)"), &mut resolution).build();
+                        turntable.width = resolution[0].max(0) as u32;
+                        turntable.height = resolution[1].max(0) as u32;
+
+                        turntable.output = turntable_output.to_str().to_string();
+                        match &turntable_recorder {
+                            Some(recorder) => ui.progress_bar(recorder.progress()).build(),
+                            None => record_turntable = ui.small_button(im_str!("Record")),
+                        }
+                    }
+
+                    if ui.collapsing_header(im_str!("Export line art")).build() {
+                        ui.input_text(im_str!("File (.svg/.pdf)"), &mut export_path).build();
+                        ui.checkbox(im_str!("Hidden line removal"), &mut export.hidden_line_removal);
+                        ui.slider_float(im_str!("Stroke scale"), &mut export.stroke_scale, 1.0, 500.0).build();
+                        ui.color_edit(im_str!("Page color"), &mut export.background_color).build();
+                        if ui.small_button(im_str!("Export")) {
+                            export.path = export_path.to_str().to_string();
+                            export_request = true;
+                        }
+                    }
+
+                    if ui.collapsing_header(im_str!("Profiler")).build() {
+                        ui.checkbox(im_str!("Measure frames"), &mut profiler_settings.enabled);
+                        ui.checkbox(im_str!("Statistics overlay"), &mut profiler_settings.show_statistics);
+                        let profiler = profiler.borrow();
+                        let history = profiler.history();
+                        if !history.is_empty() {
+                            let frames = history.len() as f32;
+                            let cpu_frames : Vec<f32> = history.iter().map(|f| f.cpu_frame_ms).collect();
+                            let gpu_total = history.iter().map(|f| f.gpu_total_ms()).sum::<f32>() / frames;
+                            ui.text(format!("CPU frame {:.2}ms, GPU {:.2}ms (average of {} frames)", cpu_frames.iter().sum::<f32>() / frames, gpu_total, history.len()));
+                            ui.plot_lines(im_str!("CPU frame"), &cpu_frames).graph_size((0.0, 40.0)).build();
+
+                            // GPU time per pass, nested passes are indented below their parent
+                            for path in profiler.scope_paths() {
+                                let gpu : Vec<f32> = history.iter().map(|f| f.scope(&path).map(|s| s.gpu_ms).unwrap_or(0.0)).collect();
+                                let cpu = history.iter().map(|f| f.scope(&path).map(|s| s.cpu_ms).unwrap_or(0.0)).sum::<f32>() / frames;
+                                let name = path.rsplit('/').next().unwrap_or(&path);
+                                let indent = "  ".repeat(path.matches('/').count());
+                                let overlay = imgui::ImString::new(format!("{}{}: GPU {:.3}ms, CPU {:.3}ms", indent, name, gpu.iter().sum::<f32>() / frames, cpu));
+                                ui.plot_lines(&imgui::ImString::new(format!("##{}", path)), &gpu).overlay_text(&overlay).graph_size((0.0, 30.0)).build();
+                            }
+                        }
+                        ui.input_text(im_str!("CSV file"), &mut profile_csv_path).build();
+                        export_profile = ui.small_button(im_str!("Export CSV"));
+                    }
+
+                    if ui.collapsing_header(im_str!("Debug output")).build() {
+                        ui.checkbox(im_str!("Show console"), &mut debug_settings.show_console);
+                        if let Ok(log) = debug_log.lock() {
+                            let errors = log.messages().iter().filter(|m| m.is_error).count();
+                            ui.text(format!("{} messages, {} errors", log.messages().len(), errors));
+                        }
+                    }
+
+                    if ui.collapsing_header(im_str!("Presets")).build() {
+                        ui.combo(im_str!("Preset"), &mut curr_preset, &preset_label_refs, 10);
+                        load_preset = ui.small_button(im_str!("Load"));
+                        ui.input_text(im_str!("Name"), &mut preset_name).build();
+                        save_preset = ui.small_button(im_str!("Save as preset"));
+                        ui.separator();
+                        save_settings = ui.button(im_str!("Save settings"), (0.0, 0.0));
+                    }
+
+                    if ui.collapsing_header(im_str!("Shader parameters")).build() {
+                        match WireframeMode::from_int(curr_item as u32) {
+                            WireframeMode::None => crate::ui::uniform_editor::draw(&ui, &pipelines.default),
+                            WireframeMode::SinglePass | WireframeMode::SinglePassCorrection => crate::ui::uniform_editor::draw(&ui, &pipelines.singlepass),
+                            WireframeMode::MultiPass => {
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.default);
+                                crate::ui::uniform_editor::draw(&ui, if lines.quads { &pipelines.lines } else { &pipelines.wireframe });
+                            },
+                            WireframeMode::FeatureEdges => {
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.default);
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.lines);
+                            },
+                            WireframeMode::UvSpace => {
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.uv_background);
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.uv_wireframe);
+                            },
+                            WireframeMode::Heatmap => crate::ui::uniform_editor::draw(&ui, &pipelines.heatmap),
+                            WireframeMode::ScreenSpace => {
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.gbuffer);
+                                crate::ui::uniform_editor::draw(&ui, &pipelines.edges);
+                            },
+                        }
+                    }
                 });
 
+            let mut debug_filter_changed = false;
+            if debug_settings.show_console {
+                let mut open = true;
+                ui.window(im_str!("GL debug output"))
+                    .size((520.0, 380.0), ImGuiCond::FirstUseEver)
+                    .opened(&mut open)
+                    .build(|| debug_filter_changed = debug_console.draw(&ui, &debug_log, debug_settings));
+                debug_settings.show_console = open;
+            }
+
+            // Counters of the previous frame, the current one is still being recorded
+            if profiler_settings.show_statistics {
+                let stats = device::stats::last_frame();
+                ui.window(im_str!("Statistics"))
+                    .position((size.0 as f32 - 340.0, 10.0), ImGuiCond::FirstUseEver)
+                    .always_auto_resize(true)
+                    .opened(&mut profiler_settings.show_statistics)
+                    .build(|| {
+                        let draw = &stats.draw;
+                        ui.text(format!("Draw calls:      {}", draw.draw_calls));
+                        ui.text(format!("Triangles:       {}", draw.triangles));
+                        ui.text(format!("Vertices:        {}", draw.vertices));
+                        ui.text(format!("State changes:   {}", draw.state_changes));
+                        ui.text(format!("Uniform uploads: {}", draw.uniform_uploads));
+
+                        ui.separator();
+                        for kind in device::stats::ObjectKind::ALL.iter() {
+                            ui.text(format!("{:<16} {}", format!("{}:", kind.name()), stats.objects(*kind)));
+                        }
+
+                        ui.separator();
+                        let cpu_ms = stats.executes.iter().map(|e| e.cpu_ms).sum::<f32>();
+                        ui.text(format!("{} command lists executed in {:.3}ms", stats.executes.len(), cpu_ms));
+                        for (i, e) in stats.executes.iter().enumerate() {
+                            let name = if e.name.is_empty() { format!("#{}", i) } else { e.name.clone() };
+                            ui.text(format!("{:<22} {:>3} commands, {:>3} draws, {:>8} triangles, {:.3}ms", name, e.commands, e.draw.draw_calls, e.draw.triangles, e.cpu_ms));
+                        }
+                    });
+            }
+
             if prv_mesh != active_mesh_index {
-                // Reconstruct the mesh
-                let (vertices,indices) = load_mesh(mesh_list[active_mesh_index as usize]).unwrap();
-                unsafe {
-                    gl::BindBuffer(gl::ARRAY_BUFFER, suzanne_vertex_buffer);
-                    let buffer_size = vertices.len() * std::mem::size_of::<GlVert>();
-                    gl::BufferData(
-                        gl::ARRAY_BUFFER,
-                        buffer_size as isize,
-                        vertices.as_ptr() as *const std::ffi::c_void,
-                        gl::STATIC_DRAW,
-                    );
-
-                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, suzanne_index_buffer);
-                    let buffer_size = indices.len() * std::mem::size_of::<u32>();
-                    gl::BufferData(
-                        gl::ELEMENT_ARRAY_BUFFER,
-                        buffer_size as isize,
-                        indices.as_ptr() as *const std::ffi::c_void,
-                        gl::STATIC_DRAW,
-                    );
+                open_request = Some(mesh_list[active_mesh_index as usize].clone());
+            }
+            if reload_mesh {
+                open_request = Some(settings.mesh.clone());
+            }
+            if heatmap_changed {
+                gpu_mesh.upload_metrics(&mesh_data.analysis.heatmap_values(settings.render.heatmap_metric));
+            }
+            if load_texture {
+                settings.uv_view.texture = uv_texture_path.to_str().to_string();
+                uv_texture = load_uv_texture(&settings.uv_view.texture);
+            }
+
+            if record_turntable {
+                turntable_recorder = Some(turntable::Recorder::new(&settings, size));
+            }
+
+            if let Some(path) = open_request {
+                // Loading happens in the background, the current mesh stays until it finishes
+                mesh_loader.request(&path, &settings.processing);
+                if !mesh_list.contains(&path) {
+                    mesh_list.push(path.clone());
+                    mesh_labels.push(mesh_label(&path));
+                }
+                active_mesh_index = mesh_list.iter().position(|m| *m == path).unwrap_or(0) as i32;
+            }
+
+            if let Some(completed) = mesh_loader.poll() {
+                match completed.result {
+                    Ok(data) => {
+                        gpu_mesh.upload(&data);
+                        gpu_mesh.upload_metrics(&data.analysis.heatmap_values(settings.render.heatmap_metric));
+                        command_lists = record_command_lists(&gl, &pipelines, &gpu_mesh);
+                        mesh_data = data;
+                        settings.add_recent_file(&completed.path);
+                        settings.mesh = completed.path;
+                    },
+                    Err(e) => {
+                        // Keep showing the previous mesh
+                        println!("Failed to load \"{}\": {}", completed.path, e);
+                        active_mesh_index = mesh_list.iter().position(|m| *m == settings.mesh).unwrap_or(0) as i32;
+                    }
                 }
             }
 
             // Update draw mode using the IMGUI result
-            draw_mode = WireframeMode::from_int(curr_item as u32);
+            settings.render.mode = WireframeMode::from_int(curr_item as u32);
+
+            if load_preset {
+                if let Some(name) = preset_names.get(curr_preset as usize) {
+                    settings.apply_preset(name);
+                    curr_item = settings.render.mode.to_int() as i32;
+                    gpu_mesh.upload_metrics(&mesh_data.analysis.heatmap_values(settings.render.heatmap_metric));
+                }
+            }
+            if save_preset && !preset_name.to_str().is_empty() {
+                settings.store_preset(preset_name.to_str());
+            }
+            if export_profile {
+                settings.profiler.csv_path = profile_csv_path.to_str().to_string();
+                let path = std::path::Path::new(&settings.profiler.csv_path);
+                match profiler.borrow().write_csv(path) {
+                    Ok(_) => println!("Wrote \"{}\"", path.display()),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            if debug_filter_changed {
+                apply_debug_settings(&debug_log, &settings.debug);
+                if settings.debug.synchronous_output() != debug_synchronous {
+                    debug_synchronous = settings.debug.synchronous_output();
+                    gl.borrow().set_debug_synchronous(debug_synchronous);
+                }
+            }
+            if save_settings {
+                settings.window.width = size.0;
+                settings.window.height = size.1;
+                match settings.save(&config_path) {
+                    Ok(_) => println!("Saved settings to \"{}\"", config_path.display()),
+                    Err(e) => println!("{}", e),
+                }
+            }
+        }
+
+        // The benchmark switches the mesh and the mode, a mesh that fails to load is skipped
+        if let Some(benchmark) = &mut benchmark {
+            while let Some((path, mode)) = benchmark.current() {
+                if path == settings.mesh {
+                    settings.render.mode = mode;
+                    curr_item = mode.to_int() as i32;
+                    break;
+                }
+                match mesh::load_mesh(&path, &settings.processing) {
+                    Ok(data) => {
+                        gpu_mesh.upload(&data);
+                        gpu_mesh.upload_metrics(&data.analysis.heatmap_values(settings.render.heatmap_metric));
+                        command_lists = record_command_lists(&gl, &pipelines, &gpu_mesh);
+                        mesh_data = data;
+                        settings.mesh = path;
+                    },
+                    Err(e) => {
+                        eprintln!("Skipping \"{}\" in the benchmark: {}", path, e);
+                        benchmark.skip_mesh();
+                    }
+                }
+            }
         }
 
+        update_msaa_target(&gl, &mut msaa_target, size, settings.antialiasing.msaa_samples);
+
         let model = na::rotation(elapsed as f32, &na::Vec3::new(0.0, 1.0, 0.0));
-        let aspect = size.0 as f32 / size.1 as f32;
-        let projection = na::Mat4::new_perspective(aspect, 3.14 / 4.0, 0.01, 1000.0);
+        let scene_view = SceneView {
+            model,
+            view_projection: settings.camera.view_projection(size.0, size.1),
+            width: size.0,
+            height: size.1,
+            tile: FULL_TILE,
+            pixel_scale: 1.0,
+            antialiased: true,
+            target: None,
+            scissor: None,
+            time: elapsed as f32,
+        };
+        let scene = Scene {
+            gl: &gl,
+            pipelines: &pipelines,
+            command_lists: &command_lists,
+            gpu_mesh: &gpu_mesh,
+            settings: &settings,
+            uv_texture: &uv_texture,
+            gbuffer: &gbuffer,
+            profiler: &profiler,
+        };
 
-        unsafe {
-            gl::Viewport(0, 0, size.0 as i32, size.1 as i32);
-            // gl.borrow().set_viewport(0,0, size.0 as i32, size.1 as i32);
-            // gl.borrow().clear([0.0,0.0,0.0,0.0],0.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-            let final_mat = projection * &view * model;
-            // Render our loaded mesh
-            gl::BindVertexArray(suzanne_vao);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, suzanne_index_buffer);
-
-            // Execute rendering code for certain modes
-            match draw_mode {
-                WireframeMode::None => {
-                    // First draw
-                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
-
-                    // Manually update some uniforms
-                    let p = &default_program;
-                    p.set_uniform("model", ShaderUniform::Mat4(model.into()));
-                    p.set_uniform("projection", ShaderUniform::Mat4(final_mat.into()));
-                    default_program.flush();
-
-                    // Execute the command list
-                    default_list.execute(&gl);
-                },
-                WireframeMode::SinglePass | WireframeMode::SinglePassCorrection => {
-                    wireframe_singlepass.set_uniform("u_line_thickness", ShaderUniform::Float(line_thickness));
-                    match draw_mode {
-                        WireframeMode::SinglePassCorrection => {
-                            wireframe_singlepass.set_uniform("u_correction", ShaderUniform::Int(1));
-                        }
-                        _ => {
-                            wireframe_singlepass.set_uniform("u_correction", ShaderUniform::Int(0));
+        // Render the next turntable frame offscreen, the rotation steps by a fixed amount per frame
+        if let Some(recorder) = &mut turntable_recorder {
+            let result = record_turntable_frame(recorder, &scene, size);
+            if result.is_err() || recorder.finished() {
+                let recorder = turntable_recorder.take().unwrap();
+                match result.and_then(|_| recorder.finish()) {
+                    Ok(written) => println!("Recorded turntable {}", written),
+                    Err(e) => println!("Turntable recording failed: {}", e),
+                }
+            }
+        }
+
+        if export_request {
+            let path = std::path::PathBuf::from(&settings.export.path);
+            if let Err(e) = export_line_art(&path, &mesh_data, &(scene_view.view_projection * model), size, &settings) {
+                println!("{}", e);
+            }
+        }
+
+        match &mut benchmark {
+            Some(benchmark) => {
+                benchmark.begin_frame();
+                let view = SceneView {
+                    view_projection: settings.camera.view_projection(benchmark_size.0, benchmark_size.1),
+                    width: benchmark_size.0,
+                    height: benchmark_size.1,
+                    ..scene_view.clone()
+                };
+                render_offscreen(&scene, &view, &benchmark.target);
+            },
+            None => render_frame(&scene, &scene_view, msaa_target.as_ref()),
+        }
+
+        // Screenshots without the UI are taken before it is drawn
+        let screenshot = &settings.screenshot;
+        if screenshot_request && !(screenshot.include_ui && screenshot.scale <= 1) {
+            take_screenshot(&scene, &scene_view);
+            screenshot_request = false;
+        }
+
+        // Write the final frame when running headless, the UI is not part of the capture
+        if options.headless() {
+            if let Some(mut b) = benchmark.take() {
+                profiler.borrow_mut().end_frame();
+                device::stats::end_frame();
+                b.end_frame(&profiler.borrow());
+                if b.finished() {
+                    let output = b.output().map(|p| p.to_path_buf());
+                    let results = b.finish(&mut profiler.borrow_mut());
+                    print!("{}", benchmark::table(&results));
+                    if let Some(output) = output {
+                        match benchmark::write_json(&output, &results, benchmark_size) {
+                            Ok(_) => println!("Wrote \"{}\"", output.display()),
+                            Err(e) => eprintln!("{}", e),
                         }
                     }
-                    wireframe_singlepass.set_uniform("projection", ShaderUniform::Mat4(final_mat.into()));
-                    wireframe_singlepass.set_uniform("model", ShaderUniform::Mat4(model.into()));
-                    wireframe_singlepass.set_uniform("u_object_color", ShaderUniform::Float4(solid_color.into()));
-                    wireframe_singlepass.set_uniform("u_wireframe_color", ShaderUniform::Float4(line_color.into()));
-                    wireframe_singlepass.flush();
+                    break 'app;
+                }
+                benchmark = Some(b);
+                window.gl_swap_window();
+                continue 'app;
+            }
 
-                    singlepass_list.execute(&gl);
+            frame_index += 1;
+            if frame_index >= options.frames && turntable_recorder.is_none() {
+                if let Some(output) = &options.output {
+                    match save_capture(output, options.scale, false, &scene, &scene_view) {
+                        Ok(_) => println!("Wrote \"{}\"", output.display()),
+                        Err(e) => eprintln!("{}", e),
+                    }
                 }
-                WireframeMode::MultiPass => {
-                    //#TODO: Rebuild command list if needed?
-                    default_program.set_uniform("projection", ShaderUniform::Mat4(final_mat.into()));
-                    default_program.set_uniform("model", ShaderUniform::Mat4(model.into()));
-
-                    wireframe_program.set_uniform("projection", ShaderUniform::Mat4(final_mat.into()));
-                    wireframe_program.set_uniform("model", ShaderUniform::Mat4(model.into()));
-
-                    default_program.flush();
-                    wireframe_program.flush();
-                    gl::LineWidth(line_thickness);
-                    multipass_list.execute(&gl);
+                if let Some(export) = &options.export {
+                    if let Err(e) = export_line_art(export, &mesh_data, &(scene_view.view_projection * model), size, &settings) {
+                        eprintln!("{}", e);
+                    }
                 }
+                break 'app;
             }
+            profiler.borrow_mut().end_frame();
+            device::stats::end_frame();
+            window.gl_swap_window();
+            continue 'app;
+        }
 
-            // Setup our Imgui rendering
-            let width = window.size().0 as f32;
-            let height = window.size().1 as f32;
-            let matrix = na::Mat4::from([
-                [(2.0 / width) as f32, 0.0, 0.0, 0.0],
-                [0.0, -(2.0 / height) as f32, 0.0, 0.0],
-                [0.0, 0.0, -1.0, 0.0],
-                [-1.0, 1.0, 0.0, 1.0],
-            ]);
-            // Initiate the draw for all lists
-            imgui_renderer.render(&matrix, ui);
+        // Setup our Imgui rendering
+        let width = window.size().0 as f32;
+        let height = window.size().1 as f32;
+        let matrix = na::Mat4::from([
+            [(2.0 / width) as f32, 0.0, 0.0, 0.0],
+            [0.0, -(2.0 / height) as f32, 0.0, 0.0],
+            [0.0, 0.0, -1.0, 0.0],
+            [-1.0, 1.0, 0.0, 1.0],
+        ]);
+        // Offscreen captures may have changed the target, the UI goes on top of the scene
+        window_pass(&gl, size, &RenderPassDesc::load()).execute(&gl);
+        // Initiate the draw for all lists
+        scene.profile("ui", || imgui_renderer.render(&matrix, ui));
+
+        if screenshot_request {
+            take_screenshot(&scene, &scene_view);
         }
 
+        profiler.borrow_mut().end_frame();
+        device::stats::end_frame();
         window.gl_swap_window();
     }
 }
@@ -397,6 +1804,48 @@ mod helpers {
         }
     }
 
+    /// Names an object for the debug output and frame capture tools
+    pub fn object_label(identifier : GLenum, name : GLuint, label : &str) {
+        if name == 0 || !gl::ObjectLabel::is_loaded() {
+            return;
+        }
+        unsafe {
+            gl::ObjectLabel(identifier, name, label.len() as GLsizei, label.as_ptr() as *const GLchar);
+        }
+    }
+
+    pub fn error_name(error : GLenum) -> &'static str {
+        match error {
+            gl::INVALID_ENUM => "GL_INVALID_ENUM",
+            gl::INVALID_VALUE => "GL_INVALID_VALUE",
+            gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+            gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+            gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+            gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+            _ => "unknown error",
+        }
+    }
+
+    /// Starts a named group of calls, shown as a tree in frame capture tools
+    pub fn push_debug_group(name : &str) {
+        if !gl::PushDebugGroup::is_loaded() {
+            return;
+        }
+        unsafe {
+            gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, name.len() as GLsizei, name.as_ptr() as *const GLchar);
+        }
+    }
+
+    pub fn pop_debug_group() {
+        if !gl::PopDebugGroup::is_loaded() {
+            return;
+        }
+        unsafe {
+            gl::PopDebugGroup();
+        }
+    }
+
     /// Allocates a byte buffer for usage with opengl error info logs
     pub fn alloc_buffer(len: usize) -> Vec<u8> {
         let mut buffer = Vec::with_capacity(len as usize + 1);
@@ -407,7 +1856,7 @@ mod helpers {
     pub fn log_gl_errors() {
         if let Err(errors) = check_gl_errors() {
             for e in errors {
-                println!("GL ERROR: {}", e);
+                println!("GL ERROR: {} (0x{:x})", error_name(e), e);
             }
         }
     }
@@ -467,148 +1916,3 @@ mod shaders {
         Ok(id)
     }
 }
-
-#[repr(C)]
-struct GlVert {
-    pos: [f32; 4],
-    norm: [f32; 4],
-    tangent: [f32; 4],
-    bitangent: [f32; 4],
-    uv: [f32; 2],
-}
-impl GlVert {
-    unsafe fn setup_vao(vtx: GLuint) -> GLuint {
-        let mut vao = 0;
-        gl::GenVertexArrays(1, &mut vao);
-
-        gl::BindVertexArray(vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vtx);
-
-        let struct_size = std::mem::size_of::<GlVert>() as i32;
-
-        gl::EnableVertexArrayAttrib(vao, 0);
-        //Position 
-        gl::VertexAttribPointer(0, 4, gl::FLOAT, gl::FALSE, struct_size, std::ptr::null());
-
-        // Normal
-        gl::EnableVertexArrayAttrib(vao, 1);
-        gl::VertexAttribPointer(
-            1,
-            4,
-            gl::FLOAT,
-            gl::FALSE,
-            struct_size,
-            (4 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-        );
-
-        // Tangent
-        gl::EnableVertexArrayAttrib(vao, 2);
-        gl::VertexAttribPointer(
-            2,
-            4,
-            gl::FLOAT,
-            gl::FALSE,
-            struct_size,
-            (8 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-        );
-
-        // Bitangent
-        gl::EnableVertexArrayAttrib(vao, 3);
-        gl::VertexAttribPointer(
-            3,
-            4,
-            gl::FLOAT,
-            gl::FALSE,
-            struct_size,
-            (12 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-        );
-
-        // UV
-        gl::EnableVertexArrayAttrib(vao, 4);
-        gl::VertexAttribPointer(
-            4,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            struct_size,
-            (16 * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
-        );
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        vao
-    }
-}
-
-fn load_mesh(path : &str) -> Result<(Vec<GlVert>, Vec<u32>), String> {
-    use assimp::Importer;
-
-    let importer = Importer::new();
-    let scene = importer.read_file(path)?;
-    println!("Loaded scene with {} meshes", scene.num_meshes());
-
-    if scene.mesh(0).is_none() {
-        return Err(String::from("Failed to find a mesh on index 0"));
-    }
-
-    let first_mesh = scene.mesh(0).unwrap();
-    println!(
-        "{} vertices, {} faces",
-        first_mesh.num_vertices(),
-        first_mesh.num_faces()
-    );
-
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-    for i in 0..first_mesh.num_vertices() {
-        let mut pos = assimp::Vector3D::new(0.0, 0.0, 0.0);
-        let mut norm = assimp::Vector3D::new(0.0, 0.0, 0.0);
-        let mut tan = assimp::Vector3D::new(0.0, 0.0, 0.0);
-        let mut bitangent = assimp::Vector3D::new(0.0, 0.0, 0.0);
-
-        if first_mesh.has_positions() {
-            pos = first_mesh.get_vertex(i).unwrap();
-        }
-
-        if first_mesh.has_normals() {
-            norm = first_mesh.get_normal(i).unwrap();
-        }
-
-        if first_mesh.has_tangents_and_bitangents() {
-            tan = first_mesh.get_tangent(i).unwrap();
-            bitangent = first_mesh.get_bitangent(i).unwrap();
-        }
-
-        vertices.push(GlVert {
-            pos: [pos.x, pos.y, pos.z, 1.0],
-            norm: [norm.x, norm.y, norm.z, 0.0],
-            tangent: [tan.x, tan.y, tan.z, 0.0],
-            bitangent: [bitangent.x, bitangent.y, bitangent.z, 0.0],
-            uv: [0.0, 0.0],
-        });
-    }
-    for face in first_mesh.face_iter() {
-        // Triangulate?
-        if face.num_indices == 4 {
-            let i0 = face[0];
-            let i1 = face[1];
-            let i2 = face[2];
-            let i3 = face[3];
-            indices.push(i0);
-            indices.push(i1);
-            indices.push(i2);
-
-            indices.push(i0);
-            indices.push(i2);
-            indices.push(i3);
-        } else {
-            let i0 = face[0];
-            let i1 = face[1];
-            let i2 = face[2];
-            indices.push(i0);
-            indices.push(i1);
-            indices.push(i2);
-        }
-    }
-
-    Ok((vertices,indices))
-}
\ No newline at end of file
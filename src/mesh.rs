@@ -0,0 +1,480 @@
+/// Mesh loading and GPU resources for meshes
+use gl::types::*;
+use time::PreciseTime;
+
+use crate::mesh_processing::{self, NormalMode, ProcessingOptions};
+use crate::mesh_analysis::{self, MeshAnalysis};
+use crate::mesh_cache;
+use crate::mesh_edges;
+use crate::device::stats::{self, ObjectKind};
+
+/// Extensions of the model formats offered by the file browser and baked by `bake`. assimp imports more
+/// formats, those can still be opened by dropping them on the window.
+pub const SUPPORTED_EXTENSIONS : [&str; 16] = [
+    "obj", "fbx", "dae", "gltf", "glb", "3ds", "blend", "ply",
+    "stl", "x", "ms3d", "lwo", "lws", "off", "ase", "md5mesh",
+];
+
+/// Returns true when the file has an extension assimp is expected to import
+pub fn is_supported(path : &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| SUPPORTED_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct GlVert {
+    pub pos: [f32; 4],
+    pub norm: [f32; 4],
+    pub tangent: [f32; 4],
+    pub bitangent: [f32; 4],
+    pub uv: [f32; 2],
+    pub uv1: [f32; 2],
+    pub color: [f32; 4],
+}
+impl GlVert {
+    /// Attribute location and float component count of every field, in memory order
+    pub const LAYOUT : [(u32, u32); 7] = [
+        (0, 4), // Position
+        (1, 4), // Normal
+        (2, 4), // Tangent
+        (3, 4), // Bitangent
+        (4, 2), // UV
+        (5, 2), // Second UV set
+        (6, 4), // Vertex color
+    ];
+
+    pub unsafe fn setup_vao(vtx: GLuint) -> GLuint {
+        let mut vao = 0;
+        gl::GenVertexArrays(1, &mut vao);
+        stats::object_created(ObjectKind::VertexArray, 1);
+
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vtx);
+
+        let struct_size = std::mem::size_of::<GlVert>() as i32;
+
+        let mut offset = 0;
+        for &(location, components) in GlVert::LAYOUT.iter() {
+            gl::EnableVertexArrayAttrib(vao, location);
+            gl::VertexAttribPointer(
+                location,
+                components as i32,
+                gl::FLOAT,
+                gl::FALSE,
+                struct_size,
+                (offset * std::mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+            offset += components as usize;
+        }
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        vao
+    }
+}
+
+/// Statistics gathered while importing a mesh, shown in the UI
+#[derive(Clone, Default)]
+pub struct ImportStats {
+    pub path : String,
+    pub meshes : u32,
+    pub vertices : u32,
+    pub faces : u32,
+    pub uv_sets : u32,
+    pub has_colors : bool,
+    pub generated_normals : bool,
+    pub generated_tangents : bool,
+    /// Vertex counts before and after welding (equal when welding is disabled)
+    pub vertices_before_weld : u32,
+    pub vertices_after_weld : u32,
+    /// Average cache miss ratio before and after the vertex cache optimization
+    pub acmr_before : f32,
+    pub acmr_after : f32,
+    pub triangles : u32,
+    pub from_cache : bool,
+    pub load_time_ms : i64,
+}
+
+/// CPU side mesh data, ready to be uploaded to the GPU
+pub struct MeshData {
+    pub vertices : Vec<GlVert>,
+    pub indices : Vec<u32>,
+    pub stats : ImportStats,
+    pub analysis : MeshAnalysis,
+    /// Unique edges for the line renderer as a line list and their per-edge values
+    pub edge_indices : Vec<u32>,
+    pub edges : Vec<mesh_edges::GpuEdge>,
+}
+
+impl MeshData {
+    /// Mesh without statistics, analysis and edges, those are filled in while loading
+    pub fn new(vertices : Vec<GlVert>, indices : Vec<u32>) -> MeshData {
+        MeshData {
+            vertices,
+            indices,
+            stats: ImportStats::default(),
+            analysis: MeshAnalysis::default(),
+            edge_indices: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+/// Stages of loading a mesh, reported through the progress callback
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoadStage {
+    ReadingCache,
+    Importing,
+    Processing,
+    GeneratingNormals,
+    GeneratingTangents,
+    Welding,
+    Optimizing,
+    Analyzing,
+    ExtractingEdges,
+}
+
+impl LoadStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoadStage::ReadingCache => "Reading cache",
+            LoadStage::Importing => "Importing",
+            LoadStage::Processing => "Processing",
+            LoadStage::GeneratingNormals => "Generating normals",
+            LoadStage::GeneratingTangents => "Generating tangents",
+            LoadStage::Welding => "Welding",
+            LoadStage::Optimizing => "Optimizing",
+            LoadStage::Analyzing => "Analyzing",
+            LoadStage::ExtractingEdges => "Extracting edges",
+        }
+    }
+}
+
+pub fn load_mesh(path : &str, options : &ProcessingOptions) -> Result<MeshData, String> {
+    load_mesh_with_progress(path, options, &mut |_, _| {})
+}
+
+/// Loads the first mesh of the file, `progress` is called with the current stage and its progress (0-1).
+/// A valid mesh cache is used instead of importing when caching is enabled, otherwise the cache is written.
+pub fn load_mesh_with_progress(path : &str, options : &ProcessingOptions, progress : &mut dyn FnMut(LoadStage, f32)) -> Result<MeshData, String> {
+    let load_start = PreciseTime::now();
+
+    let cached = if options.use_cache {
+        progress(LoadStage::ReadingCache, 0.0);
+        mesh_cache::load(path, options)
+    } else {
+        None
+    };
+    let from_cache = cached.is_some();
+
+    let mut mesh = match cached {
+        Some(mesh) => mesh,
+        None => {
+            let mesh = import_mesh(path, options, progress)?;
+            if options.use_cache {
+                if let Err(e) = mesh_cache::write(path, options, &mesh) {
+                    println!("{}", e);
+                }
+            }
+            mesh
+        }
+    };
+
+    progress(LoadStage::Analyzing, 0.0);
+    mesh.analysis = mesh_analysis::analyze(&mesh.vertices, &mesh.indices);
+
+    progress(LoadStage::ExtractingEdges, 0.0);
+    let (edge_indices, edges) = mesh_edges::gpu_data(&mesh_edges::extract(&mesh.vertices, &mesh.indices));
+    mesh.edge_indices = edge_indices;
+    mesh.edges = edges;
+
+    mesh.stats.from_cache = from_cache;
+    mesh.stats.load_time_ms = load_start.to(PreciseTime::now()).num_milliseconds();
+    Ok(mesh)
+}
+
+/// Imports the first mesh of the file with assimp and runs the processing steps
+pub fn import_mesh(path : &str, options : &ProcessingOptions, progress : &mut dyn FnMut(LoadStage, f32)) -> Result<MeshData, String> {
+    use assimp::Importer;
+
+    progress(LoadStage::Importing, 0.0);
+    let importer = Importer::new();
+    let scene = importer.read_file(path)?;
+    progress(LoadStage::Importing, 1.0);
+    println!("Loaded scene with {} meshes", scene.num_meshes());
+
+    if scene.mesh(0).is_none() {
+        return Err(String::from("Failed to find a mesh on index 0"));
+    }
+
+    let first_mesh = scene.mesh(0).unwrap();
+    println!(
+        "{} vertices, {} faces",
+        first_mesh.num_vertices(),
+        first_mesh.num_faces()
+    );
+
+    // Vertices and faces are reported as a single processing stage
+    let work = (first_mesh.num_vertices() + first_mesh.num_faces()).max(1) as f32;
+    let report_interval = 4096;
+
+    // Only the first two UV sets and the first color set are imported
+    let uv_sets = (0..2).take_while(|&set| first_mesh.has_texture_coords(set)).count();
+    let has_colors = first_mesh.has_vertex_colors(0);
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..first_mesh.num_vertices() {
+        if i % report_interval == 0 {
+            progress(LoadStage::Processing, i as f32 / work);
+        }
+
+        let mut pos = assimp::Vector3D::new(0.0, 0.0, 0.0);
+        let mut norm = assimp::Vector3D::new(0.0, 0.0, 0.0);
+        let mut tan = assimp::Vector3D::new(0.0, 0.0, 0.0);
+        let mut bitangent = assimp::Vector3D::new(0.0, 0.0, 0.0);
+
+        if first_mesh.has_positions() {
+            pos = first_mesh.get_vertex(i).unwrap();
+        }
+
+        if first_mesh.has_normals() {
+            norm = first_mesh.get_normal(i).unwrap();
+        }
+
+        if first_mesh.has_tangents_and_bitangents() {
+            tan = first_mesh.get_tangent(i).unwrap();
+            bitangent = first_mesh.get_bitangent(i).unwrap();
+        }
+
+        let mut uv = [[0.0, 0.0]; 2];
+        for set in 0..uv_sets {
+            if let Some(coord) = first_mesh.get_texture_coord(set, i) {
+                uv[set] = [coord.x, coord.y];
+            }
+        }
+
+        let mut color = [1.0, 1.0, 1.0, 1.0];
+        if has_colors {
+            if let Some(c) = first_mesh.get_vertex_color(0, i) {
+                color = [c.r, c.g, c.b, c.a];
+            }
+        }
+
+        vertices.push(GlVert {
+            pos: [pos.x, pos.y, pos.z, 1.0],
+            norm: [norm.x, norm.y, norm.z, 0.0],
+            tangent: [tan.x, tan.y, tan.z, 0.0],
+            bitangent: [bitangent.x, bitangent.y, bitangent.z, 0.0],
+            uv: uv[0],
+            uv1: uv[1],
+            color,
+        });
+    }
+    for (i, face) in first_mesh.face_iter().enumerate() {
+        if i as u32 % report_interval == 0 {
+            progress(LoadStage::Processing, (first_mesh.num_vertices() + i as u32) as f32 / work);
+        }
+
+        // Points and lines have no surface, polygons are split into a fan around their first vertex
+        let count = face.num_indices as isize;
+        for k in 1..count - 1 {
+            indices.push(face[0]);
+            indices.push(face[k]);
+            indices.push(face[k + 1]);
+        }
+    }
+
+    progress(LoadStage::Processing, 1.0);
+
+    let mut mesh = MeshData::new(vertices, indices);
+
+    // Generate the attributes the file didn't provide
+    let generate_normals = match options.normals {
+        NormalMode::Import => !first_mesh.has_normals(),
+        _ => true,
+    };
+    if generate_normals {
+        progress(LoadStage::GeneratingNormals, 0.0);
+        match options.normals {
+            NormalMode::Flat => mesh_processing::generate_flat_normals(&mut mesh),
+            _ => mesh_processing::generate_smooth_normals(&mut mesh, options.smoothing_angle),
+        }
+    }
+
+    let vertices_before_weld = mesh.vertices.len() as u32;
+    if options.weld {
+        progress(LoadStage::Welding, 0.0);
+        mesh_processing::weld_vertices(&mut mesh, options);
+    }
+    let vertices_after_weld = mesh.vertices.len() as u32;
+
+    // Imported tangents are no longer valid when the normals changed
+    let generate_tangents = options.generate_tangents && (generate_normals || !first_mesh.has_tangents_and_bitangents());
+    if generate_tangents {
+        progress(LoadStage::GeneratingTangents, 0.0);
+        mesh_processing::generate_tangents(&mut mesh, uv_sets > 0);
+    }
+
+    let acmr_before = mesh_processing::acmr(&mesh.indices);
+    if options.optimize_vertex_cache {
+        progress(LoadStage::Optimizing, 0.0);
+        mesh_processing::optimize_vertex_cache(&mut mesh);
+    }
+    let acmr_after = mesh_processing::acmr(&mesh.indices);
+
+    mesh.stats = ImportStats {
+        path: path.to_string(),
+        meshes: scene.num_meshes(),
+        vertices: first_mesh.num_vertices(),
+        faces: first_mesh.num_faces(),
+        uv_sets: uv_sets as u32,
+        has_colors,
+        generated_normals: generate_normals,
+        generated_tangents: generate_tangents,
+        vertices_before_weld,
+        vertices_after_weld,
+        acmr_before,
+        acmr_after,
+        triangles: (mesh.indices.len() / 3) as u32,
+        from_cache: false,
+        load_time_ms: 0,
+    };
+
+    Ok(mesh)
+}
+
+/// Vertex and index buffers of the mesh that is currently displayed
+pub struct GpuMesh {
+    pub vertex_buffer : GLuint,
+    pub index_buffer : GLuint,
+    pub vao : GLuint,
+    pub vertex_count : u32,
+    pub index_count : u32,
+
+    // Per-triangle values for the heatmap, bound as shader storage buffer
+    pub metrics_buffer : GLuint,
+
+    // Unique edges as a line list and their face normals for the line renderer
+    pub edge_index_buffer : GLuint,
+    pub edge_buffer : GLuint,
+    pub edge_count : u32,
+}
+
+impl GpuMesh {
+    pub fn new(data : &MeshData) -> GpuMesh {
+        let mut vertex_buffer = 0;
+        let mut index_buffer = 0;
+        let mut metrics_buffer = 0;
+        let mut edge_index_buffer = 0;
+        let mut edge_buffer = 0;
+        // Created rather than generated so they can be labelled before their first bind
+        unsafe {
+            gl::CreateBuffers(1, &mut vertex_buffer);
+            gl::CreateBuffers(1, &mut index_buffer);
+            gl::CreateBuffers(1, &mut metrics_buffer);
+            gl::CreateBuffers(1, &mut edge_index_buffer);
+            gl::CreateBuffers(1, &mut edge_buffer);
+        }
+        stats::object_created(ObjectKind::Buffer, 5);
+        crate::helpers::object_label(gl::BUFFER, vertex_buffer, "mesh vertices");
+        crate::helpers::object_label(gl::BUFFER, index_buffer, "mesh indices");
+        crate::helpers::object_label(gl::BUFFER, metrics_buffer, "mesh metrics");
+        crate::helpers::object_label(gl::BUFFER, edge_index_buffer, "mesh edge indices");
+        crate::helpers::object_label(gl::BUFFER, edge_buffer, "mesh edges");
+
+        let mut mesh = GpuMesh {
+            vertex_buffer,
+            index_buffer,
+            vao: unsafe { GlVert::setup_vao(vertex_buffer) },
+            vertex_count: 0,
+            index_count: 0,
+            metrics_buffer,
+            edge_index_buffer,
+            edge_buffer,
+            edge_count: 0,
+        };
+        crate::helpers::object_label(gl::VERTEX_ARRAY, mesh.vao, "mesh vertex layout");
+        mesh.upload(data);
+        mesh
+    }
+
+    /// Replaces the buffer contents with new mesh data, the buffer handles stay the same
+    pub fn upload(&mut self, data : &MeshData) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+            let buffer_size = data.vertices.len() * std::mem::size_of::<GlVert>();
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                buffer_size as isize,
+                data.vertices.as_ptr() as *const std::ffi::c_void,
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer);
+            let buffer_size = data.indices.len() * std::mem::size_of::<u32>();
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                buffer_size as isize,
+                data.indices.as_ptr() as *const std::ffi::c_void,
+                gl::STATIC_DRAW,
+            );
+        }
+        self.vertex_count = data.vertices.len() as u32;
+        self.index_count = data.indices.len() as u32;
+
+        unsafe {
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.edge_index_buffer);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                (data.edge_indices.len() * std::mem::size_of::<u32>()) as isize,
+                data.edge_indices.as_ptr() as *const std::ffi::c_void,
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.edge_buffer);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                (data.edges.len().max(1) * std::mem::size_of::<mesh_edges::GpuEdge>()) as isize,
+                if data.edges.is_empty() { std::ptr::null() } else { data.edges.as_ptr() as *const std::ffi::c_void },
+                gl::STATIC_DRAW,
+            );
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+
+            // Leave the triangle indices bound for the draws that don't bind their own
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer);
+        }
+        self.edge_count = data.edges.len() as u32;
+    }
+
+    /// Uploads one heatmap value per triangle
+    pub fn upload_metrics(&self, values : &[f32]) {
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.metrics_buffer);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                (values.len().max(1) * std::mem::size_of::<f32>()) as isize,
+                if values.is_empty() { std::ptr::null() } else { values.as_ptr() as *const std::ffi::c_void },
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vertex_buffer);
+            gl::DeleteBuffers(1, &self.index_buffer);
+            gl::DeleteBuffers(1, &self.metrics_buffer);
+            gl::DeleteBuffers(1, &self.edge_index_buffer);
+            gl::DeleteBuffers(1, &self.edge_buffer);
+        }
+        stats::object_deleted(ObjectKind::VertexArray, 1);
+        stats::object_deleted(ObjectKind::Buffer, 5);
+    }
+}
@@ -0,0 +1,312 @@
+/// Mesh quality analysis
+/// ---
+/// Per-triangle metrics and topology checks used to find problems in assets.
+/// The results are shown in the UI and as a heatmap on the mesh.
+use std::collections::HashMap;
+
+use crate::mesh::GlVert;
+use crate::mesh_math::{cross, dot, length, position_key, sub};
+
+/// Triangles with a smaller angle (degrees) are considered skinny
+pub const SKINNY_ANGLE : f32 = 10.0;
+
+/// Triangles with a smaller area are considered degenerate
+pub const DEGENERATE_AREA : f32 = 1e-10;
+
+/// Aspect ratios are clamped to this value for the heatmap
+const MAX_HEATMAP_ASPECT : f32 = 10.0;
+
+/// Metric shown by the heatmap
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeatmapMetric {
+    AspectRatio,
+    MinAngle,
+    Area,
+    Problems,
+}
+
+impl HeatmapMetric {
+    pub fn to_int(self) -> i32 {
+        match self {
+            HeatmapMetric::AspectRatio => 0,
+            HeatmapMetric::MinAngle => 1,
+            HeatmapMetric::Area => 2,
+            HeatmapMetric::Problems => 3,
+        }
+    }
+
+    pub fn from_int(value : i32) -> HeatmapMetric {
+        match value {
+            1 => HeatmapMetric::MinAngle,
+            2 => HeatmapMetric::Area,
+            3 => HeatmapMetric::Problems,
+            _ => HeatmapMetric::AspectRatio,
+        }
+    }
+}
+
+/// Metrics of a single triangle
+#[derive(Clone, Copy, Default)]
+pub struct TriangleMetrics {
+    /// 1.0 for an equilateral triangle, grows as the triangle gets more stretched
+    pub aspect_ratio : f32,
+    /// Smallest interior angle in degrees
+    pub min_angle : f32,
+    pub area : f32,
+    pub skinny : bool,
+    pub degenerate : bool,
+    /// Face winding disagrees with the vertex normals
+    pub flipped : bool,
+    /// Touches an edge shared by more than two triangles
+    pub non_manifold : bool,
+}
+
+/// Minimum, average and maximum of a metric over all triangles
+#[derive(Clone, Copy, Default)]
+pub struct Range {
+    pub min : f32,
+    pub avg : f32,
+    pub max : f32,
+}
+
+impl Range {
+    fn from_values<I : Iterator<Item = f32>>(values : I) -> Range {
+        let mut range = Range { min: std::f32::MAX, avg: 0.0, max: std::f32::MIN };
+        let mut count = 0;
+        for v in values {
+            range.min = range.min.min(v);
+            range.max = range.max.max(v);
+            range.avg += v;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Range::default();
+        }
+        range.avg /= count as f32;
+        range
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MeshAnalysis {
+    pub triangles : Vec<TriangleMetrics>,
+
+    pub aspect_ratio : Range,
+    pub min_angle : Range,
+    pub area : Range,
+
+    pub skinny_triangles : u32,
+    pub degenerate_triangles : u32,
+    pub flipped_triangles : u32,
+    /// Vertices sharing their position with an earlier vertex
+    pub duplicate_vertices : u32,
+    /// Edges used by only one triangle
+    pub boundary_edges : u32,
+    /// Edges used by more than two triangles
+    pub non_manifold_edges : u32,
+}
+
+/// Angle in degrees between two edges leaving the same corner
+fn corner_angle(a : [f32; 3], b : [f32; 3]) -> f32 {
+    let denominator = length(a) * length(b);
+    if denominator <= 0.0 {
+        return 0.0;
+    }
+    (dot(a, b) / denominator).max(-1.0).min(1.0).acos().to_degrees()
+}
+
+fn measure_triangle(vertices : &[GlVert], tri : &[u32]) -> TriangleMetrics {
+    let v0 = &vertices[tri[0] as usize];
+    let v1 = &vertices[tri[1] as usize];
+    let v2 = &vertices[tri[2] as usize];
+
+    let e01 = sub(v1.pos, v0.pos);
+    let e12 = sub(v2.pos, v1.pos);
+    let e20 = sub(v0.pos, v2.pos);
+
+    let normal = cross(e01, sub(v2.pos, v0.pos));
+    let area = length(normal) * 0.5;
+
+    let lengths = [length(e01), length(e12), length(e20)];
+    let longest = lengths[0].max(lengths[1]).max(lengths[2]);
+    let perimeter = lengths[0] + lengths[1] + lengths[2];
+
+    // Normalized so an equilateral triangle has an aspect ratio of 1
+    let aspect_ratio = if area > DEGENERATE_AREA {
+        longest * perimeter / (4.0 * 3.0f32.sqrt() * area)
+    } else {
+        std::f32::INFINITY
+    };
+
+    let min_angle = corner_angle(e01, [-e20[0], -e20[1], -e20[2]])
+        .min(corner_angle(e12, [-e01[0], -e01[1], -e01[2]]))
+        .min(corner_angle(e20, [-e12[0], -e12[1], -e12[2]]));
+
+    // Compare the winding with the average vertex normal, meshes without normals are never flipped
+    let vertex_normal = [
+        v0.norm[0] + v1.norm[0] + v2.norm[0],
+        v0.norm[1] + v1.norm[1] + v2.norm[1],
+        v0.norm[2] + v1.norm[2] + v2.norm[2],
+    ];
+    let flipped = area > DEGENERATE_AREA && dot(normal, vertex_normal) < 0.0;
+
+    let degenerate = area <= DEGENERATE_AREA;
+    TriangleMetrics {
+        aspect_ratio,
+        min_angle,
+        area,
+        skinny: !degenerate && min_angle < SKINNY_ANGLE,
+        degenerate,
+        flipped,
+        non_manifold: false,
+    }
+}
+
+/// Analyzes an indexed triangle list. Topology checks work on positions so
+/// vertices that are only split for their attributes are treated as one.
+pub fn analyze(vertices : &[GlVert], indices : &[u32]) -> MeshAnalysis {
+    let mut triangles : Vec<TriangleMetrics> = indices.chunks(3)
+        .filter(|tri| tri.len() == 3)
+        .map(|tri| measure_triangle(vertices, tri))
+        .collect();
+
+    // Map every vertex to the first vertex with the same position
+    let mut first_with_position : HashMap<[u32; 3], u32> = HashMap::new();
+    let mut duplicate_vertices = 0;
+    let welded : Vec<u32> = vertices.iter().enumerate()
+        .map(|(i, v)| {
+            let first = *first_with_position.entry(position_key(v)).or_insert(i as u32);
+            if first != i as u32 {
+                duplicate_vertices += 1;
+            }
+            first
+        })
+        .collect();
+
+    // Count how many triangles use each (undirected) edge
+    let mut edge_faces : HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (face, tri) in indices.chunks(3).enumerate().filter(|(_, tri)| tri.len() == 3) {
+        for corner in 0..3 {
+            let a = welded[tri[corner] as usize];
+            let b = welded[tri[(corner + 1) % 3] as usize];
+            if a == b {
+                continue;
+            }
+            let key = if a < b { (a, b) } else { (b, a) };
+            edge_faces.entry(key).or_insert_with(Vec::new).push(face);
+        }
+    }
+
+    let mut boundary_edges = 0;
+    let mut non_manifold_edges = 0;
+    for faces in edge_faces.values() {
+        match faces.len() {
+            1 => boundary_edges += 1,
+            2 => {},
+            _ => {
+                non_manifold_edges += 1;
+                for &face in faces {
+                    triangles[face].non_manifold = true;
+                }
+            }
+        }
+    }
+
+    // Degenerate triangles would dominate the ranges, leave them out
+    let valid = || triangles.iter().filter(|t| !t.degenerate);
+    MeshAnalysis {
+        aspect_ratio: Range::from_values(valid().map(|t| t.aspect_ratio)),
+        min_angle: Range::from_values(valid().map(|t| t.min_angle)),
+        area: Range::from_values(valid().map(|t| t.area)),
+        skinny_triangles: triangles.iter().filter(|t| t.skinny).count() as u32,
+        degenerate_triangles: triangles.iter().filter(|t| t.degenerate).count() as u32,
+        flipped_triangles: triangles.iter().filter(|t| t.flipped).count() as u32,
+        duplicate_vertices,
+        boundary_edges,
+        non_manifold_edges,
+        triangles,
+    }
+}
+
+impl MeshAnalysis {
+    /// Per-triangle values in [0,1] for the heatmap, 0 is good and 1 is bad
+    pub fn heatmap_values(&self, metric : HeatmapMetric) -> Vec<f32> {
+        let max_area = self.area.max.max(DEGENERATE_AREA);
+        self.triangles.iter()
+            .map(|t| {
+                match metric {
+                    HeatmapMetric::AspectRatio => ((t.aspect_ratio.min(MAX_HEATMAP_ASPECT) - 1.0) / (MAX_HEATMAP_ASPECT - 1.0)).max(0.0),
+                    HeatmapMetric::MinAngle => 1.0 - (t.min_angle / 60.0).min(1.0),
+                    // Small triangles are highlighted
+                    HeatmapMetric::Area => 1.0 - (t.area / max_area).min(1.0),
+                    HeatmapMetric::Problems => {
+                        if t.degenerate || t.flipped || t.non_manifold {
+                            1.0
+                        } else if t.skinny {
+                            0.5
+                        } else {
+                            0.0
+                        }
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertices(positions : &[[f32; 3]]) -> Vec<GlVert> {
+        positions.iter()
+            .map(|p| GlVert { pos: [p[0], p[1], p[2], 1.0], norm: [0.0, 0.0, 1.0, 0.0], ..GlVert::default() })
+            .collect()
+    }
+
+    #[test]
+    fn single_triangle_is_all_boundary() {
+        let analysis = analyze(&vertices(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]), &[0, 1, 2]);
+
+        assert_eq!(analysis.boundary_edges, 3);
+        assert_eq!(analysis.non_manifold_edges, 0);
+        assert_eq!(analysis.degenerate_triangles, 0);
+    }
+
+    #[test]
+    fn shared_edge_of_split_vertices() {
+        // The second triangle has its own copies of the diagonal's vertices
+        let analysis = analyze(&vertices(&[
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+        ]), &[0, 1, 2, 3, 4, 5]);
+
+        // Six triangle edges, four on the boundary leaves one edge shared by both triangles
+        assert_eq!(analysis.boundary_edges, 4);
+        assert_eq!(analysis.non_manifold_edges, 0);
+        assert_eq!(analysis.duplicate_vertices, 2);
+    }
+
+    #[test]
+    fn three_triangles_on_one_edge_are_non_manifold() {
+        let analysis = analyze(&vertices(&[
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0],
+            [0.5, 1.0, 0.0], [0.5, -1.0, 0.0], [0.5, 0.0, 1.0],
+        ]), &[0, 1, 2, 1, 0, 3, 0, 1, 4]);
+
+        assert_eq!(analysis.non_manifold_edges, 1);
+        assert_eq!(analysis.boundary_edges, 6);
+        assert!(analysis.triangles.iter().all(|t| t.non_manifold));
+    }
+
+    #[test]
+    fn zero_area_triangle_is_degenerate() {
+        let analysis = analyze(&vertices(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]]), &[0, 1, 2]);
+
+        assert_eq!(analysis.degenerate_triangles, 1);
+        assert!(analysis.triangles[0].degenerate);
+        assert!(!analysis.triangles[0].skinny);
+    }
+}
@@ -0,0 +1,324 @@
+/// Binary mesh cache
+/// ---
+/// Processed meshes are stored next to their source as `<file>.wfmesh` so they can be loaded
+/// without going through assimp again. The cache is only used when the size and modification
+/// time of the source file and the hash of the processing options match the ones it was written
+/// with, so checking it never reads the source file.
+///
+/// Layout (little endian):
+///   magic "WFMC", version u32
+///   source size u64, source modification time u64 (nanoseconds since the epoch)
+///   processing options hash u64
+///   vertex stride u32, attribute count u32, (location u32, components u32) per attribute
+///   bounds min [f32; 3], bounds max [f32; 3]
+///   vertex count u32, index count u32
+///   import statistics
+///   vertex data, index data
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::mesh::{self, GlVert, ImportStats, MeshData};
+use crate::mesh_processing::ProcessingOptions;
+
+pub const EXTENSION : &str = "wfmesh";
+const MAGIC : &[u8; 4] = b"WFMC";
+const VERSION : u32 = 2;
+
+const FLAG_HAS_COLORS : u32 = 1;
+const FLAG_GENERATED_NORMALS : u32 = 2;
+const FLAG_GENERATED_TANGENTS : u32 = 4;
+
+/// Path of the cache file belonging to a mesh
+pub fn cache_path(path : &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path, EXTENSION))
+}
+
+/// FNV-1a, stable between runs unlike the std hasher
+fn hash_bytes(bytes : &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Size and modification time of the source file
+fn source_key(path : &str) -> Result<(u64, u64), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to read \"{}\": {}", path, e))?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() * 1_000_000_000 + u64::from(d.subsec_nanos()))
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+/// Hash of the options that change the processed mesh. `use_cache` only decides whether the
+/// cache is read, baked caches are written with it off and loaded with it on.
+fn hash_options(options : &ProcessingOptions) -> u64 {
+    let options = ProcessingOptions {
+        use_cache: false,
+        ..options.clone()
+    };
+    hash_bytes(toml::to_string(&options).unwrap_or_default().as_bytes())
+}
+
+fn bounds(vertices : &[GlVert]) -> ([f32; 3], [f32; 3]) {
+    if vertices.is_empty() {
+        return ([0.0; 3], [0.0; 3]);
+    }
+
+    let mut min = [std::f32::MAX; 3];
+    let mut max = [std::f32::MIN; 3];
+    for v in vertices {
+        for i in 0..3 {
+            min[i] = min[i].min(v.pos[i]);
+            max[i] = max[i].max(v.pos[i]);
+        }
+    }
+    (min, max)
+}
+
+struct Writer {
+    data : Vec<u8>,
+}
+
+impl Writer {
+    fn u32(&mut self, v : u32) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v : u64) {
+        self.data.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v : f32) {
+        self.u32(v.to_bits());
+    }
+
+    /// Appends the raw memory of `items`, the cache is only meant for little endian machines
+    fn raw<T : Copy>(&mut self, items : &[T]) {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(items.as_ptr() as *const u8, items.len() * std::mem::size_of::<T>())
+        };
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+struct Reader<'a> {
+    data : &'a [u8],
+    offset : usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len : usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(self.bytes(4)?);
+        Some(u32::from_le_bytes(b))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Some(u64::from_le_bytes(b))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    fn raw<T : Copy>(&mut self, count : usize) -> Option<Vec<T>> {
+        let bytes = self.bytes(count.checked_mul(std::mem::size_of::<T>())?)?;
+        let mut items = Vec::with_capacity(count);
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), items.as_mut_ptr() as *mut u8, bytes.len());
+            items.set_len(count);
+        }
+        Some(items)
+    }
+}
+
+/// Writes the processed mesh to the cache file of `path`
+pub fn write(path : &str, options : &ProcessingOptions, mesh : &MeshData) -> Result<(), String> {
+    let stats = &mesh.stats;
+    let (min, max) = bounds(&mesh.vertices);
+
+    let mut w = Writer { data: Vec::new() };
+    w.data.extend_from_slice(MAGIC);
+    w.u32(VERSION);
+    let (size, modified) = source_key(path)?;
+    w.u64(size);
+    w.u64(modified);
+    w.u64(hash_options(options));
+
+    w.u32(std::mem::size_of::<GlVert>() as u32);
+    w.u32(GlVert::LAYOUT.len() as u32);
+    for &(location, components) in GlVert::LAYOUT.iter() {
+        w.u32(location);
+        w.u32(components);
+    }
+
+    min.iter().chain(max.iter()).for_each(|v| w.f32(*v));
+    w.u32(mesh.vertices.len() as u32);
+    w.u32(mesh.indices.len() as u32);
+
+    let mut flags = 0;
+    if stats.has_colors { flags |= FLAG_HAS_COLORS; }
+    if stats.generated_normals { flags |= FLAG_GENERATED_NORMALS; }
+    if stats.generated_tangents { flags |= FLAG_GENERATED_TANGENTS; }
+    w.u32(stats.meshes);
+    w.u32(stats.faces);
+    w.u32(stats.uv_sets);
+    w.u32(flags);
+    w.u32(stats.vertices_before_weld);
+    w.u32(stats.vertices_after_weld);
+    w.f32(stats.acmr_before);
+    w.f32(stats.acmr_after);
+
+    w.raw(&mesh.vertices);
+    w.raw(&mesh.indices);
+
+    let cache = cache_path(path);
+    std::fs::write(&cache, &w.data).map_err(|e| format!("Failed to write mesh cache \"{}\": {}", cache.display(), e))
+}
+
+/// Loads the cached mesh of `path`, returns `None` when there is no cache or it is out of date
+pub fn load(path : &str, options : &ProcessingOptions) -> Option<MeshData> {
+    let file = File::open(cache_path(path)).ok()?;
+    let map = unsafe { memmap::Mmap::map(&file) }.ok()?;
+    let mut r = Reader { data: &map[..], offset: 0 };
+
+    if r.bytes(4)? != MAGIC || r.u32()? != VERSION {
+        return None;
+    }
+    let source = (r.u64()?, r.u64()?);
+    if r.u64()? != hash_options(options) || source_key(path).ok()? != source {
+        return None;
+    }
+
+    if r.u32()? as usize != std::mem::size_of::<GlVert>() || r.u32()? as usize != GlVert::LAYOUT.len() {
+        return None;
+    }
+    for &(location, components) in GlVert::LAYOUT.iter() {
+        if r.u32()? != location || r.u32()? != components {
+            return None;
+        }
+    }
+
+    // Bounds are only needed by external tools
+    r.bytes(6 * 4)?;
+    let vertex_count = r.u32()? as usize;
+    let index_count = r.u32()? as usize;
+
+    let meshes = r.u32()?;
+    let faces = r.u32()?;
+    let uv_sets = r.u32()?;
+    let flags = r.u32()?;
+    let vertices_before_weld = r.u32()?;
+    let vertices_after_weld = r.u32()?;
+    let acmr_before = r.f32()?;
+    let acmr_after = r.f32()?;
+
+    let vertices = r.raw::<GlVert>(vertex_count)?;
+    let indices = r.raw::<u32>(index_count)?;
+    if indices.iter().any(|i| *i as usize >= vertex_count) {
+        return None;
+    }
+
+    let stats = ImportStats {
+        path: path.to_string(),
+        meshes,
+        vertices: vertex_count as u32,
+        faces,
+        uv_sets,
+        has_colors: flags & FLAG_HAS_COLORS != 0,
+        generated_normals: flags & FLAG_GENERATED_NORMALS != 0,
+        generated_tangents: flags & FLAG_GENERATED_TANGENTS != 0,
+        vertices_before_weld,
+        vertices_after_weld,
+        acmr_before,
+        acmr_after,
+        triangles: (index_count / 3) as u32,
+        from_cache: true,
+        load_time_ms: 0,
+    };
+
+    Some(MeshData { stats, ..MeshData::new(vertices, indices) })
+}
+
+/// Writes caches for every supported mesh in `dir`, returns the number of baked meshes
+pub fn bake_directory(dir : &Path, options : &ProcessingOptions) -> Result<usize, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read directory \"{}\": {}", dir.display(), e))?;
+
+    let mut paths : Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && mesh::is_supported(p))
+        .collect();
+    paths.sort();
+
+    let mut baked = 0;
+    for path in paths {
+        let path = path.to_string_lossy().to_string();
+        match mesh::import_mesh(&path, options, &mut |_, _| {}) {
+            Ok(mesh) => match write(&path, options, &mesh) {
+                Ok(()) => {
+                    println!("Baked {} ({} vertices, {} triangles)", path, mesh.vertices.len(), mesh.indices.len() / 3);
+                    baked += 1;
+                },
+                Err(e) => println!("{}", e),
+            },
+            Err(e) => println!("Failed to import \"{}\": {}", path, e),
+        }
+    }
+    Ok(baked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn triangle() -> MeshData {
+        let vertex = |x : f32, y : f32| GlVert { pos: [x, y, 0.0, 1.0], norm: [0.0, 0.0, 1.0, 0.0], uv: [x, y], ..GlVert::default() };
+        MeshData::new(vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 1.0)], vec![0, 1, 2])
+    }
+
+    #[test]
+    fn write_and_load() {
+        let dir = std::env::temp_dir().join(format!("wireframe_mesh_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("triangle.obj");
+        std::fs::write(&source, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let path = source.to_str().unwrap();
+
+        let options = ProcessingOptions::default();
+        let mesh = triangle();
+        write(path, &options, &mesh).expect("Failed to write the cache");
+
+        let loaded = load(path, &options).expect("Failed to load the cache");
+        assert_eq!(loaded.indices, mesh.indices);
+        assert_eq!(loaded.vertices.len(), mesh.vertices.len());
+        for (a, b) in loaded.vertices.iter().zip(mesh.vertices.iter()) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.uv, b.uv);
+        }
+
+        // Different processing options
+        assert!(load(path, &ProcessingOptions { weld: true, ..options.clone() }).is_none());
+
+        // Changed source file
+        std::fs::OpenOptions::new().append(true).open(&source).unwrap().write_all(b"# changed\n").unwrap();
+        assert!(load(path, &options).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,94 @@
+/// Edge list of a mesh
+/// ---
+/// The unique edges of the triangles together with the faces next to them. The line renderer
+/// draws them as quads and uses the face normals to find boundary, crease and silhouette edges.
+use std::collections::HashMap;
+
+use crate::mesh::GlVert;
+use crate::mesh_math::position_key;
+
+/// An edge shared by one or more triangles
+#[derive(Clone, Copy)]
+pub struct Edge {
+    pub a : u32,
+    pub b : u32,
+    /// Number of triangles using the edge, 1 on a boundary and more than 2 when non-manifold
+    pub faces : u32,
+    /// Normals of the first two triangles, the second one is zero on a boundary
+    pub normals : [[f32; 3]; 2],
+    /// Middle of the edge, the view direction for the silhouette test
+    pub center : [f32; 3],
+}
+
+/// Per-edge values of the line renderer, laid out for a std430 shader storage buffer
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GpuEdge {
+    /// xyz: normal of the first face, w: face count
+    pub normal0 : [f32; 4],
+    /// xyz: normal of the second face
+    pub normal1 : [f32; 4],
+    /// xyz: middle of the edge
+    pub center : [f32; 4],
+}
+
+fn face_normal(a : &GlVert, b : &GlVert, c : &GlVert) -> [f32; 3] {
+    let p = |v : &GlVert| na::Vec3::new(v.pos[0], v.pos[1], v.pos[2]);
+    let n = (p(b) - p(a)).cross(&(p(c) - p(a)));
+    let length = n.norm();
+    if length > 1e-12 {
+        [n.x / length, n.y / length, n.z / length]
+    } else {
+        [0.0; 3]
+    }
+}
+
+/// Finds the unique edges of the triangles. Vertices are matched by position so the seams of
+/// split vertices (uv, normals) don't produce duplicate edges.
+pub fn extract(vertices : &[GlVert], indices : &[u32]) -> Vec<Edge> {
+    let mut by_position : HashMap<[u32; 3], u32> = HashMap::new();
+    let canonical : Vec<u32> = vertices.iter().enumerate().map(|(i, v)| {
+        *by_position.entry(position_key(v)).or_insert(i as u32)
+    }).collect();
+
+    let mut lookup : HashMap<(u32, u32), usize> = HashMap::new();
+    let mut edges : Vec<Edge> = Vec::new();
+    for tri in indices.chunks(3).filter(|t| t.len() == 3) {
+        let normal = face_normal(&vertices[tri[0] as usize], &vertices[tri[1] as usize], &vertices[tri[2] as usize]);
+        for i in 0..3 {
+            let a = canonical[tri[i] as usize];
+            let b = canonical[tri[(i + 1) % 3] as usize];
+            if a == b {
+                continue;
+            }
+
+            let index = *lookup.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (pa, pb) = (vertices[a as usize].pos, vertices[b as usize].pos);
+                let center = [(pa[0] + pb[0]) * 0.5, (pa[1] + pb[1]) * 0.5, (pa[2] + pb[2]) * 0.5];
+                edges.push(Edge { a, b, faces: 0, normals: [[0.0; 3]; 2], center });
+                edges.len() - 1
+            });
+            let edge = &mut edges[index];
+            if edge.faces < 2 {
+                edge.normals[edge.faces as usize] = normal;
+            }
+            edge.faces += 1;
+        }
+    }
+    edges
+}
+
+/// Line list indices and storage buffer contents for the line renderer
+pub fn gpu_data(edges : &[Edge]) -> (Vec<u32>, Vec<GpuEdge>) {
+    let mut indices = Vec::with_capacity(edges.len() * 2);
+    for e in edges {
+        indices.push(e.a);
+        indices.push(e.b);
+    }
+    let data = edges.iter().map(|e| GpuEdge {
+        normal0: [e.normals[0][0], e.normals[0][1], e.normals[0][2], e.faces as f32],
+        normal1: [e.normals[1][0], e.normals[1][1], e.normals[1][2], 0.0],
+        center: [e.center[0], e.center[1], e.center[2], 1.0],
+    }).collect();
+    (indices, data)
+}
@@ -0,0 +1,112 @@
+/// Asynchronous mesh loading
+/// ---
+/// Importing and processing happens on a worker thread, finished meshes are queued and picked up
+/// by the GL thread which does the upload. The current mesh stays visible until then.
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use crate::mesh::{self, LoadStage, MeshData};
+use crate::mesh_processing::ProcessingOptions;
+
+enum Message {
+    Progress { id : u64, stage : LoadStage, progress : f32 },
+    Finished { id : u64, result : Result<MeshData, String> },
+}
+
+/// State of the load that is currently running
+pub struct PendingLoad {
+    id : u64,
+    pub path : String,
+    pub stage : LoadStage,
+    pub progress : f32,
+}
+
+/// A finished load, ready to be uploaded on the GL thread
+pub struct CompletedLoad {
+    pub path : String,
+    pub result : Result<MeshData, String>,
+}
+
+pub struct MeshLoader {
+    sender : Sender<Message>,
+    receiver : Receiver<Message>,
+    next_id : u64,
+    pending : Option<PendingLoad>,
+}
+
+impl MeshLoader {
+    pub fn new() -> MeshLoader {
+        let (sender, receiver) = channel();
+        MeshLoader {
+            sender,
+            receiver,
+            next_id: 0,
+            pending: None,
+        }
+    }
+
+    /// Starts loading the mesh on a worker thread. A load that is still running gets superseded,
+    /// its result is discarded when it arrives.
+    pub fn request(&mut self, path : &str, options : &ProcessingOptions) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.pending = Some(PendingLoad {
+            id,
+            path: path.to_string(),
+            stage: LoadStage::Importing,
+            progress: 0.0,
+        });
+
+        let sender = self.sender.clone();
+        let path = path.to_string();
+        let options = options.clone();
+        let spawned = thread::Builder::new()
+            .name("mesh-loader".to_string())
+            .spawn(move || {
+                let progress_sender = sender.clone();
+                let result = mesh::load_mesh_with_progress(&path, &options, &mut |stage, progress| {
+                    let _ = progress_sender.send(Message::Progress { id, stage, progress });
+                });
+                let _ = sender.send(Message::Finished { id, result });
+            });
+
+        if let Err(e) = spawned {
+            let _ = self.sender.send(Message::Finished { id, result: Err(format!("Failed to start loader thread: {}", e)) });
+        }
+    }
+
+    pub fn pending(&self) -> Option<&PendingLoad> {
+        self.pending.as_ref()
+    }
+
+    /// Drains the message queue, returns the load that finished (if any)
+    pub fn poll(&mut self) -> Option<CompletedLoad> {
+        let mut completed = None;
+        while let Ok(message) = self.receiver.try_recv() {
+            let current_id = match &self.pending {
+                Some(p) => p.id,
+                None => continue,
+            };
+
+            match message {
+                Message::Progress { id, stage, progress } if id == current_id => {
+                    if let Some(p) = &mut self.pending {
+                        p.stage = stage;
+                        p.progress = progress;
+                    }
+                },
+                Message::Finished { id, result } if id == current_id => {
+                    let pending = self.pending.take().unwrap();
+                    completed = Some(CompletedLoad {
+                        path: pending.path,
+                        result,
+                    });
+                },
+                // Messages of superseded loads
+                _ => {}
+            }
+        }
+        completed
+    }
+}
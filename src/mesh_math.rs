@@ -0,0 +1,43 @@
+/// Mesh math helpers
+/// ---
+/// Small vector functions on the plain arrays of `GlVert` and the key used to find vertices that
+/// share a position, shared by the mesh processing, analysis and edge extraction.
+use crate::mesh::GlVert;
+
+/// Difference of the xyz components of two positions
+pub fn sub(a : [f32; 4], b : [f32; 4]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn cross(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn dot(a : [f32; 3], b : [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn length(a : [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Unit vector in the direction of `a`, zero for a zero vector
+pub fn normalize(a : [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > 1e-12 {
+        [a[0] / len, a[1] / len, a[2] / len]
+    } else {
+        [0.0, 0.0, 0.0]
+    }
+}
+
+/// Bit exact key for a vertex position, used to find vertices shared between faces.
+/// -0.0 and 0.0 are the same position but not the same bits, so zeros are made positive.
+pub fn position_key(v : &GlVert) -> [u32; 3] {
+    let bits = |x : f32| if x == 0.0 { 0.0f32.to_bits() } else { x.to_bits() };
+    [bits(v.pos[0]), bits(v.pos[1]), bits(v.pos[2])]
+}
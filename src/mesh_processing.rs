@@ -0,0 +1,574 @@
+/// CPU side mesh processing
+/// ---
+/// Generation of normals and tangent frames for meshes that don't provide them,
+/// vertex welding and vertex cache optimization.
+/// Tangents are generated with MikkTSpace so they match what bakers expect; `tangent.w` holds the
+/// handedness and the bitangent is `cross(normal, tangent) * tangent.w`.
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::mesh::{GlVert, MeshData};
+use crate::mesh_math::{cross, dot, normalize, position_key, sub};
+
+/// How normals are treated after importing
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalMode {
+    /// Use the normals from the file, smooth normals are generated when the file has none
+    Import,
+    /// Smooth normals, faces are only smoothed together when the angle between them is below the threshold
+    Smooth,
+    /// One normal per face
+    Flat,
+}
+
+impl NormalMode {
+    pub fn to_int(self) -> i32 {
+        match self {
+            NormalMode::Import => 0,
+            NormalMode::Smooth => 1,
+            NormalMode::Flat => 2,
+        }
+    }
+
+    pub fn from_int(value : i32) -> NormalMode {
+        match value {
+            1 => NormalMode::Smooth,
+            2 => NormalMode::Flat,
+            _ => NormalMode::Import,
+        }
+    }
+}
+
+/// Options for the processing that happens after import
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessingOptions {
+    pub normals : NormalMode,
+    /// Maximum angle (degrees) between faces that get smoothed together
+    pub smoothing_angle : f32,
+    /// Generate tangents when the file does not provide them
+    pub generate_tangents : bool,
+
+    /// Merge vertices that are equal within the tolerances below
+    pub weld : bool,
+    pub weld_position_tolerance : f32,
+    /// Maximum angle (degrees) between normals of welded vertices
+    pub weld_normal_angle : f32,
+    pub weld_uv_tolerance : f32,
+
+    /// Reorder triangles for the post-transform vertex cache (Forsyth)
+    pub optimize_vertex_cache : bool,
+
+    /// Read and write the binary mesh cache next to the source file
+    pub use_cache : bool,
+}
+
+impl Default for ProcessingOptions {
+    fn default() -> Self {
+        ProcessingOptions {
+            normals: NormalMode::Import,
+            smoothing_angle: 60.0,
+            generate_tangents: true,
+            weld: false,
+            weld_position_tolerance: 1e-5,
+            weld_normal_angle: 1.0,
+            weld_uv_tolerance: 1e-5,
+            optimize_vertex_cache: false,
+            use_cache: false,
+        }
+    }
+}
+
+/// Unnormalized face normal, its length is twice the triangle area
+fn face_normal(vertices : &[GlVert], tri : &[u32]) -> [f32; 3] {
+    let p0 = vertices[tri[0] as usize].pos;
+    let p1 = vertices[tri[1] as usize].pos;
+    let p2 = vertices[tri[2] as usize].pos;
+    cross(sub(p1, p0), sub(p2, p0))
+}
+
+/// Builds a new indexed mesh from one vertex per triangle corner, identical corners are merged again
+fn rebuild_from_corners(corners : Vec<GlVert>) -> (Vec<GlVert>, Vec<u32>) {
+    let mut lookup : HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(corners.len());
+
+    for corner in corners {
+        let key = vertex_key(&corner);
+        let index = *lookup.entry(key).or_insert_with(|| {
+            vertices.push(corner);
+            (vertices.len() - 1) as u32
+        });
+        indices.push(index);
+    }
+
+    (vertices, indices)
+}
+
+/// Bit exact key of all vertex attributes
+pub fn vertex_key(v : &GlVert) -> Vec<u32> {
+    v.pos.iter()
+        .chain(v.norm.iter())
+        .chain(v.tangent.iter())
+        .chain(v.bitangent.iter())
+        .chain(v.uv.iter())
+        .chain(v.uv1.iter())
+        .chain(v.color.iter())
+        .map(|f| f.to_bits())
+        .collect()
+}
+
+/// Replaces the normals by one normal per face, vertices are split where needed
+pub fn generate_flat_normals(mesh : &mut MeshData) {
+    let mut corners = Vec::with_capacity(mesh.indices.len());
+    for tri in mesh.indices.chunks(3) {
+        let n = normalize(face_normal(&mesh.vertices, tri));
+        for &i in tri {
+            let mut v = mesh.vertices[i as usize];
+            v.norm = [n[0], n[1], n[2], 0.0];
+            corners.push(v);
+        }
+    }
+
+    let (vertices, indices) = rebuild_from_corners(corners);
+    mesh.vertices = vertices;
+    mesh.indices = indices;
+}
+
+/// Generates area weighted smooth normals. Faces sharing a position are only averaged
+/// when the angle between their normals is below `angle_threshold` (degrees).
+pub fn generate_smooth_normals(mesh : &mut MeshData, angle_threshold : f32) {
+    let cos_threshold = angle_threshold.to_radians().cos();
+
+    let face_normals : Vec<[f32; 3]> = mesh.indices.chunks(3)
+        .map(|tri| face_normal(&mesh.vertices, tri))
+        .collect();
+
+    // All faces touching each unique position
+    let mut faces_per_position : HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (face, tri) in mesh.indices.chunks(3).enumerate() {
+        for &i in tri {
+            faces_per_position.entry(position_key(&mesh.vertices[i as usize])).or_insert_with(Vec::new).push(face);
+        }
+    }
+
+    let mut corners = Vec::with_capacity(mesh.indices.len());
+    for (face, tri) in mesh.indices.chunks(3).enumerate() {
+        let own = normalize(face_normals[face]);
+        for &i in tri {
+            let mut v = mesh.vertices[i as usize];
+            let mut sum = [0.0, 0.0, 0.0];
+            for &other in &faces_per_position[&position_key(&v)] {
+                let n = face_normals[other];
+                if dot(own, normalize(n)) >= cos_threshold {
+                    sum = [sum[0] + n[0], sum[1] + n[1], sum[2] + n[2]];
+                }
+            }
+            let n = normalize(sum);
+            v.norm = [n[0], n[1], n[2], 0.0];
+            corners.push(v);
+        }
+    }
+
+    let (vertices, indices) = rebuild_from_corners(corners);
+    mesh.vertices = vertices;
+    mesh.indices = indices;
+}
+
+/// Adapter to run MikkTSpace on our indexed triangle lists
+struct TangentGeometry<'a> {
+    vertices : &'a [GlVert],
+    indices : &'a [u32],
+    // One tangent per triangle corner
+    tangents : Vec<[f32; 4]>,
+}
+
+impl<'a> mikktspace::Geometry for TangentGeometry<'a> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face : usize) -> usize {
+        3
+    }
+
+    fn position(&self, face : usize, vert : usize) -> [f32; 3] {
+        let p = self.vertices[self.indices[face * 3 + vert] as usize].pos;
+        [p[0], p[1], p[2]]
+    }
+
+    fn normal(&self, face : usize, vert : usize) -> [f32; 3] {
+        let n = self.vertices[self.indices[face * 3 + vert] as usize].norm;
+        [n[0], n[1], n[2]]
+    }
+
+    fn tex_coord(&self, face : usize, vert : usize) -> [f32; 2] {
+        self.vertices[self.indices[face * 3 + vert] as usize].uv
+    }
+
+    fn set_tangent_encoded(&mut self, tangent : [f32; 4], face : usize, vert : usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
+
+/// Generates MikkTSpace tangents and bitangents from the normals and the first UV set.
+/// Vertices are split where MikkTSpace assigns different tangents to the same vertex.
+/// Meshes without UVs get an arbitrary orthonormal frame around the normal instead.
+pub fn generate_tangents(mesh : &mut MeshData, has_uvs : bool) {
+    if !has_uvs {
+        for v in &mut mesh.vertices {
+            let n = [v.norm[0], v.norm[1], v.norm[2]];
+            let t = orthogonal(n);
+            set_tangent_frame(v, [t[0], t[1], t[2], 1.0]);
+        }
+        return;
+    }
+
+    let mut geometry = TangentGeometry {
+        vertices: &mesh.vertices,
+        indices: &mesh.indices,
+        tangents: vec![[1.0, 0.0, 0.0, 1.0]; mesh.indices.len()],
+    };
+    if !mikktspace::generate_tangents(&mut geometry) {
+        println!("MikkTSpace failed to generate tangents.");
+        return;
+    }
+
+    let tangents = geometry.tangents;
+    let corners = mesh.indices.iter().zip(tangents.iter())
+        .map(|(&i, &t)| {
+            let mut v = mesh.vertices[i as usize];
+            set_tangent_frame(&mut v, t);
+            v
+        })
+        .collect();
+
+    let (vertices, indices) = rebuild_from_corners(corners);
+    mesh.vertices = vertices;
+    mesh.indices = indices;
+}
+
+/// Stores the tangent and derives the bitangent from the normal and handedness
+fn set_tangent_frame(v : &mut GlVert, tangent : [f32; 4]) {
+    let n = [v.norm[0], v.norm[1], v.norm[2]];
+    let b = cross(n, [tangent[0], tangent[1], tangent[2]]);
+    v.tangent = tangent;
+    v.bitangent = [b[0] * tangent[3], b[1] * tangent[3], b[2] * tangent[3], 0.0];
+}
+
+/// Any unit vector perpendicular to `n`
+fn orthogonal(n : [f32; 3]) -> [f32; 3] {
+    let axis = if n[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    normalize(cross(n, axis))
+}
+
+/// Merges vertices whose position, normal and uv are within the tolerances of the options.
+/// Positions are bucketed in a grid with the position tolerance as cell size, so only
+/// vertices in neighbouring cells are compared.
+pub fn weld_vertices(mesh : &mut MeshData, options : &ProcessingOptions) {
+    let cell_size = options.weld_position_tolerance.max(1e-7);
+    let cos_normal = options.weld_normal_angle.to_radians().cos();
+    let uv_tolerance_sq = options.weld_uv_tolerance * options.weld_uv_tolerance;
+    let position_tolerance_sq = options.weld_position_tolerance * options.weld_position_tolerance;
+
+    let cell_of = |v : &GlVert| -> [i64; 3] {
+        [
+            (v.pos[0] / cell_size).floor() as i64,
+            (v.pos[1] / cell_size).floor() as i64,
+            (v.pos[2] / cell_size).floor() as i64,
+        ]
+    };
+
+    let matches = |a : &GlVert, b : &GlVert| -> bool {
+        let d = sub(a.pos, b.pos);
+        if dot(d, d) > position_tolerance_sq {
+            return false;
+        }
+
+        let na = normalize([a.norm[0], a.norm[1], a.norm[2]]);
+        let nb = normalize([b.norm[0], b.norm[1], b.norm[2]]);
+        if dot(na, nb) < cos_normal && (dot(na, na) > 0.0 || dot(nb, nb) > 0.0) {
+            return false;
+        }
+
+        // Both uv sets, merging seams of the second set would break lightmaps
+        let uv_close = |a : [f32; 2], b : [f32; 2]| {
+            let du = [a[0] - b[0], a[1] - b[1]];
+            du[0] * du[0] + du[1] * du[1] <= uv_tolerance_sq
+        };
+        uv_close(a.uv, b.uv) && uv_close(a.uv1, b.uv1) && a.color == b.color
+    };
+
+    let mut grid : HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut welded : Vec<GlVert> = Vec::new();
+    let mut remap = Vec::with_capacity(mesh.vertices.len());
+
+    for v in &mesh.vertices {
+        let cell = cell_of(v);
+        let mut found = None;
+
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(candidates) = grid.get(&[cell[0] + dx, cell[1] + dy, cell[2] + dz]) {
+                        if let Some(&c) = candidates.iter().find(|&&c| matches(&welded[c as usize], v)) {
+                            found = Some(c);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+
+        let index = match found {
+            Some(c) => c,
+            None => {
+                welded.push(*v);
+                let index = (welded.len() - 1) as u32;
+                grid.entry(cell).or_insert_with(Vec::new).push(index);
+                index
+            }
+        };
+        remap.push(index);
+    }
+
+    for i in &mut mesh.indices {
+        *i = remap[*i as usize];
+    }
+    mesh.vertices = welded;
+}
+
+/// Size of the simulated post-transform cache used for the optimization and the ACMR
+const VERTEX_CACHE_SIZE : usize = 32;
+
+/// Average cache miss ratio: transformed vertices per triangle with a FIFO cache.
+/// 3.0 is the worst case, 0.5 is about the best a regular mesh can do.
+pub fn acmr(indices : &[u32]) -> f32 {
+    let triangles = indices.len() / 3;
+    if triangles == 0 {
+        return 0.0;
+    }
+
+    let mut cache : VecDeque<u32> = VecDeque::with_capacity(VERTEX_CACHE_SIZE);
+    let mut misses = 0;
+    for &i in indices {
+        if !cache.contains(&i) {
+            misses += 1;
+            if cache.len() == VERTEX_CACHE_SIZE {
+                cache.pop_front();
+            }
+            cache.push_back(i);
+        }
+    }
+
+    misses as f32 / triangles as f32
+}
+
+/// Score of a vertex in Tom Forsyth's "Linear-Speed Vertex Cache Optimisation"
+fn forsyth_vertex_score(cache_position : i32, remaining_triangles : u32) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let mut score = 0.0;
+    if cache_position >= 0 {
+        if cache_position < 3 {
+            // The last triangle's vertices, using them again gives no benefit over any other cached vertex
+            score = 0.75;
+        } else {
+            let scale = 1.0 / (VERTEX_CACHE_SIZE - 3) as f32;
+            score = (1.0 - (cache_position - 3) as f32 * scale).powf(1.5);
+        }
+    }
+
+    // Prefer vertices with few triangles left so they can be finished
+    score + 2.0 * (remaining_triangles as f32).powf(-0.5)
+}
+
+/// Reorders the triangles to improve post-transform vertex cache hits (Forsyth).
+/// Vertices are reordered afterwards in order of first use to improve fetch locality,
+/// vertices that are not used by any triangle are dropped.
+pub fn optimize_vertex_cache(mesh : &mut MeshData) {
+    let vertex_count = mesh.vertices.len();
+    let triangle_count = mesh.indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    // Triangles using each vertex
+    let mut vertex_triangles : Vec<Vec<u32>> = vec![Vec::new(); vertex_count];
+    for t in 0..triangle_count {
+        for &i in &mesh.indices[t * 3..t * 3 + 3] {
+            vertex_triangles[i as usize].push(t as u32);
+        }
+    }
+
+    let mut vertex_score : Vec<f32> = vertex_triangles.iter().map(|t| forsyth_vertex_score(-1, t.len() as u32)).collect();
+    let mut triangle_added = vec![false; triangle_count];
+    let mut triangle_score : Vec<f32> = (0..triangle_count)
+        .map(|t| mesh.indices[t * 3..t * 3 + 3].iter().map(|&i| vertex_score[i as usize]).sum())
+        .collect();
+
+    let mut cache : Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(mesh.indices.len());
+
+    // Start with the best triangle overall, afterwards candidates come from the cache
+    let mut best = (0..triangle_count).max_by(|&a, &b| triangle_score[a].partial_cmp(&triangle_score[b]).unwrap()).map(|t| t as u32);
+    let mut scan_start = 0;
+
+    while output.len() < mesh.indices.len() {
+        let triangle = match best {
+            Some(t) => t as usize,
+            None => {
+                // Nothing left in the cache, continue with the first unadded triangle
+                while triangle_added[scan_start] {
+                    scan_start += 1;
+                }
+                scan_start
+            }
+        };
+
+        triangle_added[triangle] = true;
+        let corners = [mesh.indices[triangle * 3], mesh.indices[triangle * 3 + 1], mesh.indices[triangle * 3 + 2]];
+        output.extend_from_slice(&corners);
+
+        // Move the vertices to the front of the cache and remove the triangle from their lists
+        for &v in corners.iter().rev() {
+            vertex_triangles[v as usize].retain(|&t| t as usize != triangle);
+            cache.retain(|&c| c != v);
+            cache.insert(0, v);
+        }
+
+        // Update the scores of everything in the cache, evicted vertices lose their cache bonus
+        for (position, &v) in cache.iter().enumerate() {
+            let position = if position < VERTEX_CACHE_SIZE { position as i32 } else { -1 };
+            vertex_score[v as usize] = forsyth_vertex_score(position, vertex_triangles[v as usize].len() as u32);
+        }
+        cache.truncate(VERTEX_CACHE_SIZE);
+
+        best = None;
+        let mut best_score = -1.0;
+        for &v in &cache {
+            for &t in &vertex_triangles[v as usize] {
+                let t = t as usize;
+                let score : f32 = mesh.indices[t * 3..t * 3 + 3].iter().map(|&i| vertex_score[i as usize]).sum();
+                triangle_score[t] = score;
+                if score > best_score {
+                    best_score = score;
+                    best = Some(t as u32);
+                }
+            }
+        }
+    }
+
+    // Reorder the vertices in order of first use
+    let mut remap = vec![std::u32::MAX; vertex_count];
+    let mut vertices = Vec::with_capacity(vertex_count);
+    for i in &mut output {
+        if remap[*i as usize] == std::u32::MAX {
+            remap[*i as usize] = vertices.len() as u32;
+            vertices.push(mesh.vertices[*i as usize]);
+        }
+        *i = remap[*i as usize];
+    }
+
+    mesh.vertices = vertices;
+    mesh.indices = output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(pos : [f32; 3], uv1 : [f32; 2]) -> GlVert {
+        GlVert {
+            pos: [pos[0], pos[1], pos[2], 1.0],
+            norm: [0.0, 0.0, 1.0, 0.0],
+            uv: [pos[0], pos[1]],
+            uv1,
+            ..GlVert::default()
+        }
+    }
+
+    fn weld_options() -> ProcessingOptions {
+        ProcessingOptions { weld: true, ..ProcessingOptions::default() }
+    }
+
+    /// Unit quad as two triangles with a vertex per corner, the diagonal's vertices of the
+    /// second triangle get `seam_uv1`
+    fn split_quad(seam_uv1 : [f32; 2]) -> MeshData {
+        let vertices = vec![
+            vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+            vertex([1.0, 1.0, 0.0], [0.0, 0.0]),
+            vertex([0.0, 0.0, 0.0], seam_uv1),
+            vertex([1.0, 1.0, 0.0], seam_uv1),
+            vertex([0.0, 1.0, 0.0], [0.0, 0.0]),
+        ];
+        MeshData::new(vertices, (0..6).collect())
+    }
+
+    /// Corner positions of every triangle, sorted so meshes can be compared regardless of the order
+    fn triangle_set(mesh : &MeshData) -> Vec<[[u32; 3]; 3]> {
+        let mut triangles : Vec<[[u32; 3]; 3]> = mesh.indices.chunks(3)
+            .map(|t| [
+                position_key(&mesh.vertices[t[0] as usize]),
+                position_key(&mesh.vertices[t[1] as usize]),
+                position_key(&mesh.vertices[t[2] as usize]),
+            ])
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn weld_merges_duplicated_quad_vertices() {
+        let mut mesh = split_quad([0.0, 0.0]);
+        weld_vertices(&mut mesh, &weld_options());
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn weld_keeps_second_uv_set_seams() {
+        let mut mesh = split_quad([0.5, 0.5]);
+        weld_vertices(&mut mesh, &weld_options());
+
+        assert_eq!(mesh.vertices.len(), 6);
+    }
+
+    #[test]
+    fn optimize_keeps_triangles_and_improves_acmr() {
+        // Grid with its triangles in a scrambled order
+        let size = 16;
+        let mut vertices = Vec::new();
+        for y in 0..=size {
+            for x in 0..=size {
+                vertices.push(vertex([x as f32, y as f32, 0.0], [0.0, 0.0]));
+            }
+        }
+        let mut triangles = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let i = y * (size + 1) + x;
+                triangles.push([i, i + 1, i + size + 2]);
+                triangles.push([i, i + size + 2, i + size + 1]);
+            }
+        }
+        let mut seed = 12345u32;
+        for i in (1..triangles.len()).rev() {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            triangles.swap(i, (seed >> 8) as usize % (i + 1));
+        }
+        let mut mesh = MeshData::new(vertices, triangles.iter().flat_map(|t| t.iter().cloned()).collect());
+
+        let triangles_before = triangle_set(&mesh);
+        let acmr_before = acmr(&mesh.indices);
+        optimize_vertex_cache(&mut mesh);
+
+        assert_eq!(triangle_set(&mesh), triangles_before);
+        assert!(acmr(&mesh.indices) <= acmr_before);
+    }
+}
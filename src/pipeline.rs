@@ -13,6 +13,15 @@ pub enum FillMode {
     Lines,
     Fill
 }
+/// Comparison of the depth test
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub enum DepthCompare {
+    Less,
+    LessEqual,
+    Greater,
+}
+
 /// Available shader stages that are implemented
 #[derive(Copy, Clone)]
 pub enum ShaderStage {
@@ -26,6 +35,7 @@ pub enum ShaderStage {
 #[derive(Clone, Copy)]
 pub enum ShaderUniform{
     Invalid,
+    Bool(bool),
     Int(i32),
     Float(f32),
     Float2([f32;2]),
@@ -38,6 +48,7 @@ pub enum ShaderUniform{
 impl ShaderUniform {
     fn get_type (&self) -> UniformType {
         match self {
+            ShaderUniform::Bool(_) => UniformType::Bool,
             ShaderUniform::Int(_) => UniformType::Int,
             ShaderUniform::Float(_) => UniformType::Float,
             ShaderUniform::Float2(_) => UniformType::Float2,
@@ -52,17 +63,27 @@ impl ShaderUniform {
 
     fn from_uniform_type(uniform_type : UniformType, default : String) -> Self{
         match uniform_type {
+            UniformType::Bool => ShaderUniform::Bool(default.trim() == "true"),
             UniformType::Int => {
-                let default_value = default.parse::<i32>().unwrap_or(0);
+                let default_value = default.trim().parse::<i32>().unwrap_or(0);
                 ShaderUniform::Int(default_value)
             },
             UniformType::Float => {
-                let default_value = default.parse::<f32>().unwrap_or(0.0);
+                let default_value = default.trim().parse::<f32>().unwrap_or(0.0);
                 ShaderUniform::Float(default_value)
             },
-            UniformType::Float2 => ShaderUniform::Float2([0.0,0.0]),
-            UniformType::Float3 => ShaderUniform::Float3([0.0,0.0,0.0]),
-            UniformType::Float4 => ShaderUniform::Float4([0.0,0.0,0.0,0.0]),
+            UniformType::Float2 => {
+                let v = parse_vector(&default, 2);
+                ShaderUniform::Float2([v[0], v[1]])
+            },
+            UniformType::Float3 => {
+                let v = parse_vector(&default, 3);
+                ShaderUniform::Float3([v[0], v[1], v[2]])
+            },
+            UniformType::Float4 => {
+                let v = parse_vector(&default, 4);
+                ShaderUniform::Float4([v[0], v[1], v[2], v[3]])
+            },
             UniformType::Mat3 => ShaderUniform::Mat3(
                 [
                     [1.0,0.0,0.0],
@@ -83,8 +104,30 @@ impl ShaderUniform {
     }
 }
 
+/// Parses a GLSL vector constructor (eg: `vec4(1,1,1,1.0)`) into `count` components.
+/// A single component is splatted like GLSL does, missing components default to 0.
+fn parse_vector(source : &str, count : usize) -> Vec<f32> {
+    let source = source.trim();
+    let inner = match (source.find('('), source.rfind(')')) {
+        (Some(start), Some(end)) if start < end => &source[start + 1..end],
+        _ => source,
+    };
+
+    let components : Vec<f32> = inner
+        .split(',')
+        .filter_map(|c| c.trim().parse::<f32>().ok())
+        .collect();
+
+    if components.len() == 1 {
+        return vec![components[0]; count];
+    }
+
+    (0..count).map(|i| *components.get(i).unwrap_or(&0.0)).collect()
+}
+
 #[derive(PartialEq, Eq, Hash, Copy, Clone)]
 enum UniformType {
+    Bool,
     Int,
     Float,
     Float2,
@@ -97,18 +140,46 @@ enum UniformType {
 }
 
 
-type ShaderUniformLoc = (GLint, ShaderUniform);
+/// Reflection data gathered for each uniform declared in the shader sources
+#[derive(Clone, Copy)]
+struct UniformDesc {
+    location : GLint,
+
+    // Value assigned in the declaration (`uniform float x = 1.0;`)
+    default : ShaderUniform,
+
+    // Optional slider range taken from a `// @range(min, max)` annotation
+    range : Option<(f32, f32)>,
+
+    // Set by the application rather than the user, marked with a `// @internal` annotation
+    internal : bool,
+}
+
+/// Public view on a pipeline uniform, used to build editors for the shader parameters
+pub struct UniformInfo {
+    pub name : String,
+    pub value : ShaderUniform,
+    pub default : ShaderUniform,
+    pub range : Option<(f32, f32)>,
+    pub internal : bool,
+}
+
 pub struct Pipeline {
     program : GLuint,
 
+    // Name shown in the debug output and frame capture tools
+    label : String,
+
     // Graphics pipeline properties
     depth_test : bool,
+    depth_compare : DepthCompare,
+    depth_write : bool,
     blend_enabled : bool,
     fill_mode : FillMode,
     primitive_topology : PrimitiveTopology,
 
     // Collection of shader uniforms found when creating the pipeline
-    uniforms : RefCell<HashMap<(String, UniformType), GLint>>,
+    uniforms : RefCell<HashMap<(String, UniformType), UniformDesc>>,
 
     // Overrides for each uniform
     uniform_overrides : RefCell<HashMap<(String, UniformType), ShaderUniform>>
@@ -117,13 +188,24 @@ pub struct Pipeline {
 impl Pipeline {
     pub fn primitive_topology(&self) -> PrimitiveTopology { self.primitive_topology }
     pub fn depth_test(&self) -> bool { self.depth_test }
+    pub fn depth_compare(&self) -> DepthCompare { self.depth_compare }
+    pub fn depth_write(&self) -> bool { self.depth_write }
     pub fn blend_enabled(&self) -> bool { self.blend_enabled }
     pub fn fill_mode(&self) -> FillMode { self.fill_mode }
     pub fn program(&self) -> GLuint { self.program }
+    pub fn label(&self) -> &str { &self.label }
 
     pub fn set_blending(&mut self, enabled : bool) { self.blend_enabled = enabled; }
     pub fn set_depth_test(&mut self, enabled : bool) { self.depth_test = enabled; }
+    pub fn set_depth_compare(&mut self, compare : DepthCompare) { self.depth_compare = compare; }
+    pub fn set_depth_write(&mut self, enabled : bool) { self.depth_write = enabled; }
     pub fn set_fill_mode(&mut self, mode : FillMode) { self.fill_mode = mode; }
+    pub fn set_primitive_topology(&mut self, topology : PrimitiveTopology) { self.primitive_topology = topology; }
+
+    pub fn set_label(&mut self, label : &str) {
+        self.label = label.to_string();
+        crate::helpers::object_label(gl::PROGRAM, self.program, label);
+    }
 
     pub fn set_uniform(&self, name : &str, uniform : ShaderUniform) {
         let uniform_type = uniform.get_type();
@@ -141,30 +223,66 @@ impl Pipeline {
         }
     }
 
+    /// Returns all uniforms declared by the shaders, sorted by name.
+    /// The value is the current override or the declared default when none was set.
+    pub fn uniforms(&self) -> Vec<UniformInfo> {
+        let overrides = self.uniform_overrides.borrow();
+        let mut result : Vec<UniformInfo> = self.uniforms.borrow().iter()
+            .map(|(key, desc)| {
+                UniformInfo {
+                    name: key.0.clone(),
+                    value: *overrides.get(key).unwrap_or(&desc.default),
+                    default: desc.default,
+                    range: desc.range,
+                    internal: desc.internal,
+                }
+            })
+            .collect();
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        result
+    }
+
+    /// Restores all uniforms to the defaults declared in the shader source
+    pub fn reset_uniforms(&self) {
+        let mut overrides = self.uniform_overrides.borrow_mut();
+        for (key, desc) in self.uniforms.borrow().iter() {
+            if overrides.contains_key(key) {
+                overrides.insert(key.clone(), desc.default);
+            }
+        }
+    }
+
     /// Uploads all bound uniforms to the GPU
     pub fn flush(&self) {
         unsafe{
             gl::UseProgram(self.program);
         }
 
+        // Samplers get consecutive texture units in the order they are flushed
+        let mut texture_unit = 0;
+
         // Flushes all uniforms to the GPU
         for (key, value) in self.uniform_overrides.borrow_mut().iter() {
             if let Some(v) = self.uniforms.borrow().get(key) {
-                let shader_loc = *v;
+                let shader_loc = v.location;
                 unsafe{
                     if shader_loc != -1 {
+                        crate::device::stats::record_uniform_upload();
                         // Upload depending on shader uniform type
                         match value {
+                            ShaderUniform::Bool(v) => gl::Uniform1i(shader_loc, *v as i32),
                             ShaderUniform::Int(v) => gl::Uniform1iv(shader_loc, 1, v),
                             ShaderUniform::Float(v) => gl::Uniform1fv(shader_loc, 1, v),
+                            ShaderUniform::Float2(v) => gl::Uniform2fv(shader_loc, 1,v.as_ptr()),
                             ShaderUniform::Float3(v) => gl::Uniform3fv(shader_loc, 1,v.as_ptr()),
                             ShaderUniform::Float4(v) => gl::Uniform4fv(shader_loc, 1, v.as_ptr()),
                             ShaderUniform::Mat3(v) => gl::UniformMatrix3fv(shader_loc, 1, gl::FALSE, v[0].as_ptr()),
                             ShaderUniform::Mat4(v) => gl::UniformMatrix4fv(shader_loc, 1, gl::FALSE, v[0].as_ptr()),
                             ShaderUniform::Sampler2D(tex) => {
-                                // gl::ActiveTexture(gl::TEXTURE0 + shader_loc as u32);
                                 if *tex != 0 {
-                                    gl::BindTextureUnit(shader_loc as u32, *tex as u32);
+                                    gl::BindTextureUnit(texture_unit, *tex as u32);
+                                    gl::Uniform1i(shader_loc, texture_unit as i32);
+                                    texture_unit += 1;
                                 }
                             }
                             _ => {
@@ -181,6 +299,9 @@ impl Pipeline {
 
     pub fn create_simple_with_geom(vertex_source : &[u8], geom_source : &[u8], fragment_source : &[u8]) -> Result<Pipeline, String> {
         use std::ffi::CString;
+        let vertex_source = &resolve_includes(vertex_source)?[..];
+        let geom_source = &resolve_includes(geom_source)?[..];
+        let fragment_source = &resolve_includes(fragment_source)?[..];
         let vertex_shader   = shaders::shader_from_source(&CString::new(vertex_source).unwrap(), gl::VERTEX_SHADER)?;
         let geom_shader = shaders::shader_from_source(&CString::new(geom_source).unwrap(), gl::GEOMETRY_SHADER)?;
         let fragment_shader = shaders::shader_from_source(&CString::new(fragment_source).unwrap(), gl::FRAGMENT_SHADER)?;
@@ -189,17 +310,14 @@ impl Pipeline {
         parse_uniforms(vertex_source, &mut uniforms);
         parse_uniforms(geom_source, &mut uniforms);
         parse_uniforms(fragment_source, &mut uniforms);
-        for (key,value) in &uniforms {
-        println!("{:?}", key.0);
-        }
 
         let program = create_simple_program(vertex_shader, fragment_shader, Some(geom_shader))?;
 
         // Find locations for all uniforms
-        for (key,mut value) in &mut uniforms {
+        for (key, desc) in &mut uniforms {
             unsafe{
                 let shader_loc = gl::GetUniformLocation(program, CString::from_vec_unchecked(key.0.as_bytes().to_vec()).as_ptr());
-                *value = shader_loc;
+                desc.location = shader_loc;
             }
         }
 
@@ -207,17 +325,22 @@ impl Pipeline {
             Pipeline{
                 blend_enabled: false,
                 depth_test: true,
+                depth_compare: DepthCompare::Less,
+                depth_write: true,
                 fill_mode: FillMode::Fill,
                 primitive_topology: PrimitiveTopology::Triangles,
                 program,
                 uniforms: RefCell::new(uniforms),
-                uniform_overrides: RefCell::new(HashMap::new())
+                uniform_overrides: RefCell::new(HashMap::new()),
+                label: String::new(),
             }
         )
     }
 
     pub fn create_simple(vertex_source : &[u8], fragment_source : &[u8]) -> Result<Pipeline, String> {
         use std::ffi::CString;
+        let vertex_source = &resolve_includes(vertex_source)?[..];
+        let fragment_source = &resolve_includes(fragment_source)?[..];
 
         let vertex_shader   = shaders::shader_from_source(&CString::new(vertex_source).unwrap(), gl::VERTEX_SHADER)?;
         let fragment_shader = shaders::shader_from_source(&CString::new(fragment_source).unwrap(), gl::FRAGMENT_SHADER)?;
@@ -233,10 +356,10 @@ impl Pipeline {
         let program = create_simple_program(vertex_shader, fragment_shader, None)?;
 
         // Find locations for all uniforms
-        for (key,mut value) in &mut uniforms {
+        for (key, desc) in &mut uniforms {
             unsafe{
                 let shader_loc = gl::GetUniformLocation(program, CString::from_vec_unchecked(key.0.as_bytes().to_vec()).as_ptr());
-                *value = shader_loc;
+                desc.location = shader_loc;
             }
         }
 
@@ -244,11 +367,14 @@ impl Pipeline {
             Pipeline{
                 blend_enabled: false,
                 depth_test: true,
+                depth_compare: DepthCompare::Less,
+                depth_write: true,
                 fill_mode: FillMode::Fill,
                 primitive_topology: PrimitiveTopology::Triangles,
                 program,
                 uniforms: RefCell::new(uniforms),
-                uniform_overrides: RefCell::new(HashMap::new())
+                uniform_overrides: RefCell::new(HashMap::new()),
+                label: String::new(),
             }
         )
     }
@@ -269,6 +395,7 @@ pub fn create_simple_program( vertex_shader : GLuint, fragment_shader : GLuint,
 
     unsafe{
         let program =  gl::CreateProgram(); 
+        crate::device::stats::object_created(crate::device::stats::ObjectKind::Program, 1);
         gl::AttachShader(program, vertex_shader);
         if let Some(geom_shader) = geom {
             gl::AttachShader(program, geom_shader);
@@ -296,10 +423,38 @@ pub fn create_simple_program( vertex_shader : GLuint, fragment_shader : GLuint,
         Ok(program)
     }
 }
+
+/// Files shaders can pull in with `#include "<name>"`, embedded like the shaders themselves
+const SHADER_INCLUDES : &[(&str, &[u8])] = &[
+    ("stroke_pattern.glsl", include_bytes!("../shaders/stroke_pattern.glsl")),
+];
+
+/// Replaces the `#include "<name>"` lines of a shader source by the contents of the include
+fn resolve_includes(source : &[u8]) -> Result<Vec<u8>, String> {
+    let source = String::from_utf8_lossy(source);
+    let mut resolved = String::with_capacity(source.len());
+    for line in source.lines() {
+        if let Some(include) = line.trim().strip_prefix("#include") {
+            let name = include.trim().trim_matches('"');
+            match SHADER_INCLUDES.iter().find(|(n, _)| *n == name) {
+                Some((_, include)) => resolved.push_str(&String::from_utf8_lossy(include)),
+                None => return Err(format!("Unknown shader include \"{}\"", name)),
+            }
+        } else {
+            resolved.push_str(line);
+        }
+        resolved.push('\n');
+    }
+    Ok(resolved.into_bytes())
+}
+
 /// Simple uniform parsing of a source string (for openGL, does not allow layout bindings yet) 
-fn parse_uniforms(source : &[u8], result : &mut HashMap<(String, UniformType), GLint>){
+/// Slider ranges can be provided with an annotation comment: `uniform float x = 0.1; // @range(0.0, 1.0)`
+/// Uniforms the application sets itself are marked with `// @internal` and left out of the editor
+fn parse_uniforms(source : &[u8], result : &mut HashMap<(String, UniformType), UniformDesc>){
     // Construct the regex
-    let uniform_regex = Regex::new(r"(uniform)\s(?P<type>\w*)\s(?P<var>\w*)(\s?=\s?(?P<default>.*))?;").expect("Failed to create regex!");
+    let uniform_regex = Regex::new(r"(uniform)\s(?P<type>\w*)\s(?P<var>\w*)(\s?=\s?(?P<default>[^;]*))?;(?P<comment>[^\n]*)").expect("Failed to create regex!");
+    let range_regex = Regex::new(r"@range\(\s*(?P<min>[-+0-9.eE]+)\s*,\s*(?P<max>[-+0-9.eE]+)\s*\)").expect("Failed to create regex!");
 
     // Convert our input data to a string
     let c =  String::from_utf8(source.to_vec()).unwrap();
@@ -309,6 +464,7 @@ fn parse_uniforms(source : &[u8], result : &mut HashMap<(String, UniformType), G
     for matches in results {
         let var_name = &matches["var"];
         let var_type = match &matches["type"] {
+            "bool" => UniformType::Bool,
             "int" => UniformType::Int,
             "float" => UniformType::Float,
             "vec2" => UniformType::Float2,
@@ -320,13 +476,29 @@ fn parse_uniforms(source : &[u8], result : &mut HashMap<(String, UniformType), G
             _ => UniformType::Invalid,
         };
 
-        let var_default_value = if let Some(v) = matches.get(4) { 
+        let var_default_value = if let Some(v) = matches.name("default") { 
             ShaderUniform::from_uniform_type(var_type, v.as_str().to_string()) 
         } else {
             ShaderUniform::from_uniform_type(var_type, String::new())
         };
 
+        let range = range_regex.captures(&matches["comment"]).and_then(|r| {
+            match (r["min"].parse::<f32>(), r["max"].parse::<f32>()) {
+                (Ok(min), Ok(max)) => Some((min, max)),
+                _ => None,
+            }
+        });
+
+        let internal = matches["comment"].contains("@internal");
+
         let result_key = (var_name.to_string(), var_type);
-        result.entry(result_key).or_insert(-1);
+        let desc = result.entry(result_key).or_insert(UniformDesc {
+            location: -1,
+            default: var_default_value,
+            range,
+            internal,
+        });
+        // A uniform shared between stages is internal when any of its declarations says so
+        desc.internal |= internal;
     }
 }
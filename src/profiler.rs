@@ -0,0 +1,299 @@
+/// Frame profiler
+/// ---
+/// Measures named scopes on the CPU and on the GPU with `GL_TIME_ELAPSED` queries. Time elapsed
+/// queries can't overlap, so a scope that starts inside another one ends the query of its parent
+/// and the parent continues with a new query once the child is done. The GPU results are read
+/// back a few frames later, when they are available, and kept in a rolling history.
+use gl::types::*;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+
+use crate::device::stats::{self, ObjectKind};
+
+/// Timings of one scope in a frame, scopes that ran more than once are summed
+#[derive(Clone)]
+pub struct ScopeTime {
+    /// Names of the enclosing scopes and this one, joined by '/'
+    pub path : String,
+    pub depth : usize,
+    /// Including the nested scopes
+    pub gpu_ms : f32,
+    pub cpu_ms : f32,
+}
+
+/// Timings of a finished frame
+#[derive(Clone)]
+pub struct FrameTimes {
+    pub index : u64,
+    pub cpu_frame_ms : f32,
+    pub scopes : Vec<ScopeTime>,
+}
+
+impl FrameTimes {
+    /// GPU time of all top level scopes
+    pub fn gpu_total_ms(&self) -> f32 {
+        self.scopes.iter().filter(|s| s.depth == 0).map(|s| s.gpu_ms).sum()
+    }
+
+    pub fn scope(&self, path : &str) -> Option<&ScopeTime> {
+        self.scopes.iter().find(|s| s.path == path)
+    }
+}
+
+/// A query measuring part of a scope, the scope itself excluding its children
+struct Segment {
+    path : String,
+    query : GLuint,
+}
+
+struct OpenScope {
+    path : String,
+    start : f64,
+}
+
+/// Frame whose queries are still in flight
+struct PendingFrame {
+    index : u64,
+    cpu_frame_ms : f32,
+    segments : Vec<Segment>,
+    /// Inclusive CPU time per scope path, in the order the scopes started
+    cpu : Vec<(String, f32)>,
+}
+
+pub struct Profiler {
+    pub enabled : bool,
+    /// Number of finished frames kept in the history
+    pub history_length : usize,
+    history : VecDeque<FrameTimes>,
+    pending : VecDeque<PendingFrame>,
+    current : Option<PendingFrame>,
+    stack : Vec<OpenScope>,
+    free_queries : Vec<GLuint>,
+    frame_index : u64,
+    frame_start : f64,
+}
+
+impl Profiler {
+    pub fn new(history_length : usize) -> Profiler {
+        Profiler {
+            enabled: false,
+            history_length,
+            history: VecDeque::new(),
+            pending: VecDeque::new(),
+            current: None,
+            stack: Vec::new(),
+            free_queries: Vec::new(),
+            frame_index: 0,
+            frame_start: time::precise_time_s(),
+        }
+    }
+
+    /// Finished frames, oldest first
+    pub fn history(&self) -> &VecDeque<FrameTimes> {
+        &self.history
+    }
+
+    /// Index of the last frame started, the history gets it once its results are read back
+    pub fn last_frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Waits for the GPU and moves all finished frames to the history
+    pub fn flush(&mut self) {
+        unsafe {
+            gl::Finish();
+        }
+        self.collect();
+    }
+
+    fn query(&mut self) -> GLuint {
+        self.free_queries.pop().unwrap_or_else(|| {
+            let mut query = 0;
+            unsafe {
+                gl::GenQueries(1, &mut query);
+            }
+            stats::object_created(ObjectKind::Query, 1);
+            query
+        })
+    }
+
+    /// Starts a GPU query for the innermost open scope
+    fn begin_segment(&mut self) {
+        let path = match self.stack.last() {
+            Some(scope) => scope.path.clone(),
+            None => return,
+        };
+        let query = self.query();
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query);
+        }
+        if let Some(frame) = &mut self.current {
+            frame.segments.push(Segment { path, query });
+        }
+    }
+
+    fn end_segment(&mut self) {
+        if !self.stack.is_empty() {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.collect();
+        if !self.enabled {
+            return;
+        }
+        self.frame_index += 1;
+        self.current = Some(PendingFrame {
+            index: self.frame_index,
+            cpu_frame_ms: 0.0,
+            segments: Vec::new(),
+            cpu: Vec::new(),
+        });
+    }
+
+    /// Finishes the frame, the CPU frame time runs from the end of the previous frame
+    pub fn end_frame(&mut self) {
+        debug_assert!(self.stack.is_empty(), "Profiler scopes are still open at the end of the frame");
+        let now = time::precise_time_s();
+        let cpu_frame_ms = ((now - self.frame_start) * 1000.0) as f32;
+        self.frame_start = now;
+
+        if let Some(mut frame) = self.current.take() {
+            frame.cpu_frame_ms = cpu_frame_ms;
+            self.pending.push_back(frame);
+        }
+    }
+
+    pub fn begin_scope(&mut self, name : &str) {
+        if self.current.is_none() {
+            return;
+        }
+        self.end_segment();
+        let path = match self.stack.last() {
+            Some(parent) => format!("{}/{}", parent.path, name),
+            None => name.to_string(),
+        };
+        self.stack.push(OpenScope { path, start: time::precise_time_s() });
+        self.begin_segment();
+    }
+
+    pub fn end_scope(&mut self) {
+        if self.current.is_none() || self.stack.is_empty() {
+            return;
+        }
+        self.end_segment();
+        let scope = self.stack.pop().unwrap();
+        let cpu_ms = ((time::precise_time_s() - scope.start) * 1000.0) as f32;
+        if let Some(frame) = &mut self.current {
+            match frame.cpu.iter_mut().find(|(path, _)| *path == scope.path) {
+                Some(entry) => entry.1 += cpu_ms,
+                None => frame.cpu.push((scope.path, cpu_ms)),
+            }
+        }
+        // The parent continues with a new query
+        self.begin_segment();
+    }
+
+    /// Moves the oldest pending frames with all their results available to the history
+    fn collect(&mut self) {
+        while let Some(frame) = self.pending.front() {
+            let available = frame.segments.iter().all(|s| {
+                let mut available = 0;
+                unsafe {
+                    gl::GetQueryObjectiv(s.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                }
+                available != 0
+            });
+            if !available {
+                break;
+            }
+
+            let frame = self.pending.pop_front().unwrap();
+            let mut scopes : Vec<ScopeTime> = frame.cpu.iter().map(|(path, cpu_ms)| ScopeTime {
+                path: path.clone(),
+                depth: path.matches('/').count(),
+                gpu_ms: 0.0,
+                cpu_ms: *cpu_ms,
+            }).collect();
+
+            for segment in frame.segments {
+                let mut nanoseconds : GLuint64 = 0;
+                unsafe {
+                    gl::GetQueryObjectui64v(segment.query, gl::QUERY_RESULT, &mut nanoseconds);
+                }
+                self.free_queries.push(segment.query);
+
+                // Segments count towards their scope and every enclosing scope
+                let ms = nanoseconds as f32 / 1.0e6;
+                for scope in scopes.iter_mut() {
+                    if segment.path == scope.path || segment.path.starts_with(&format!("{}/", scope.path)) {
+                        scope.gpu_ms += ms;
+                    }
+                }
+            }
+
+            self.history.push_back(FrameTimes {
+                index: frame.index,
+                cpu_frame_ms: frame.cpu_frame_ms,
+                scopes,
+            });
+            while self.history.len() > self.history_length {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    /// Scope paths seen in the history, in the order they first appear
+    pub fn scope_paths(&self) -> Vec<String> {
+        let mut paths : Vec<String> = Vec::new();
+        for frame in self.history.iter() {
+            for scope in frame.scopes.iter() {
+                if !paths.contains(&scope.path) {
+                    paths.push(scope.path.clone());
+                }
+            }
+        }
+        paths
+    }
+
+    /// Writes the history as CSV, one row per frame and a GPU and CPU column per scope
+    pub fn write_csv(&self, path : &Path) -> Result<(), String> {
+        let paths = self.scope_paths();
+        let mut csv = String::from("frame,cpu_frame_ms,gpu_total_ms");
+        for p in paths.iter() {
+            csv.push_str(&format!(",{0} gpu_ms,{0} cpu_ms", p));
+        }
+        csv.push('\n');
+
+        for frame in self.history.iter() {
+            csv.push_str(&format!("{},{:.4},{:.4}", frame.index, frame.cpu_frame_ms, frame.gpu_total_ms()));
+            for p in paths.iter() {
+                match frame.scope(p) {
+                    Some(scope) => csv.push_str(&format!(",{:.4},{:.4}", scope.gpu_ms, scope.cpu_ms)),
+                    None => csv.push_str(",,"),
+                }
+            }
+            csv.push('\n');
+        }
+
+        let mut file = std::fs::File::create(path).map_err(|e| format!("Failed to create \"{}\": {}", path.display(), e))?;
+        file.write_all(csv.as_bytes()).map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        let mut queries = std::mem::replace(&mut self.free_queries, Vec::new());
+        for frame in self.pending.iter().chain(self.current.iter()) {
+            queries.extend(frame.segments.iter().map(|s| s.query));
+        }
+        unsafe {
+            gl::DeleteQueries(queries.len() as GLsizei, queries.as_ptr());
+        }
+        stats::object_deleted(ObjectKind::Query, queries.len());
+    }
+}
@@ -0,0 +1,736 @@
+/// Persistent render configuration
+/// ---
+/// The full render setup (draw mode, colors, thickness, camera, mesh and window size) is
+/// stored in a TOML file so captures can be reproduced. The file also holds named material presets.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::WireframeMode;
+use crate::mesh_processing::ProcessingOptions;
+use crate::mesh_analysis::HeatmapMetric;
+use crate::turntable::TurntableFormat;
+use crate::device::debug::Severity;
+
+/// Default location of the configuration file, relative to the working directory
+pub const DEFAULT_CONFIG_PATH : &str = "wireframe.toml";
+
+/// Number of entries kept in the recent files list
+const MAX_RECENT_FILES : usize = 10;
+
+/// Attribute used to color the solid mesh
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSource {
+    Solid,
+    VertexColor,
+    Uv0,
+    Uv1,
+    Normal,
+}
+
+impl ColorSource {
+    /// Value of the `u_color_source` uniform
+    pub fn to_int(self) -> i32 {
+        match self {
+            ColorSource::Solid => 0,
+            ColorSource::VertexColor => 1,
+            ColorSource::Uv0 => 2,
+            ColorSource::Uv1 => 3,
+            ColorSource::Normal => 4,
+        }
+    }
+
+    pub fn from_int(value : i32) -> ColorSource {
+        match value {
+            1 => ColorSource::VertexColor,
+            2 => ColorSource::Uv0,
+            3 => ColorSource::Uv1,
+            4 => ColorSource::Normal,
+            _ => ColorSource::Solid,
+        }
+    }
+}
+
+/// Properties of how the mesh and its wireframe are drawn (this is what presets store)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub mode : WireframeMode,
+    pub line_thickness : f32,
+    pub falloff : f32,
+    pub solid_color : [f32; 4],
+    pub line_color : [f32; 4],
+    pub color_source : ColorSource,
+    pub heatmap_metric : HeatmapMetric,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            mode: WireframeMode::None,
+            line_thickness: 0.01,
+            falloff: 0.003,
+            solid_color: [1.0, 1.0, 1.0, 1.0],
+            line_color: [0.0, 0.0, 0.0, 1.0],
+            color_source: ColorSource::Solid,
+            heatmap_metric: HeatmapMetric::AspectRatio,
+        }
+    }
+}
+
+/// Normal/tangent/bitangent lines drawn on top of any wireframe mode
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorOverlaySettings {
+    pub show_normals : bool,
+    pub show_tangents : bool,
+    pub show_bitangents : bool,
+    pub length : f32,
+    pub normal_color : [f32; 4],
+    pub tangent_color : [f32; 4],
+    pub bitangent_color : [f32; 4],
+}
+
+impl Default for VectorOverlaySettings {
+    fn default() -> Self {
+        VectorOverlaySettings {
+            show_normals: false,
+            show_tangents: false,
+            show_bitangents: false,
+            length: 0.1,
+            normal_color: [0.0, 0.0, 1.0, 1.0],
+            tangent_color: [1.0, 0.0, 0.0, 1.0],
+            bitangent_color: [0.0, 1.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl VectorOverlaySettings {
+    pub fn enabled(&self) -> bool {
+        self.show_normals || self.show_tangents || self.show_bitangents
+    }
+}
+
+/// Settings of the uv-space wireframe view
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UvViewSettings {
+    /// PNG shown behind the uv layout, a checkerboard is used when empty
+    pub texture : String,
+    pub checker_count : f32,
+    /// Size of the [0,1] square relative to the window
+    pub zoom : f32,
+    pub fill_color : [f32; 4],
+    pub out_of_range_color : [f32; 4],
+}
+
+impl Default for UvViewSettings {
+    fn default() -> Self {
+        UvViewSettings {
+            texture: String::new(),
+            checker_count: 8.0,
+            zoom: 0.8,
+            fill_color: [0.2, 0.6, 1.0, 0.25],
+            out_of_range_color: [1.0, 0.3, 0.0, 0.5],
+        }
+    }
+}
+
+impl UvViewSettings {
+    /// Scale and offset from uv space to normalized device coordinates.
+    /// The [0,1] square is centered in the window and keeps its aspect ratio.
+    pub fn uv_transform(&self, width : u32, height : u32) -> [f32; 4] {
+        let aspect = width as f32 / height.max(1) as f32;
+        let (sx, sy) = if aspect >= 1.0 {
+            (2.0 * self.zoom / aspect, 2.0 * self.zoom)
+        } else {
+            (2.0 * self.zoom, 2.0 * self.zoom * aspect)
+        };
+        [sx, sy, -sx * 0.5, -sy * 0.5]
+    }
+}
+
+/// Filter used to find the edges in the G-buffer of the screen-space mode
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeOperator {
+    Sobel,
+    Roberts,
+}
+
+impl EdgeOperator {
+    /// Value of the `u_operator` uniform
+    pub fn to_int(self) -> i32 {
+        match self {
+            EdgeOperator::Sobel => 0,
+            EdgeOperator::Roberts => 1,
+        }
+    }
+
+    pub fn from_int(value : i32) -> EdgeOperator {
+        match value {
+            1 => EdgeOperator::Roberts,
+            _ => EdgeOperator::Sobel,
+        }
+    }
+}
+
+/// Settings of the screen-space edge detection mode
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EdgeDetectionSettings {
+    pub operator : EdgeOperator,
+    /// Depth discontinuity, relative to the depth of the pixel, that counts as an edge
+    pub depth_threshold : f32,
+    /// Normal discontinuity that counts as an edge
+    pub normal_threshold : f32,
+    /// Line width in pixels, 1 to 8
+    pub line_width : f32,
+}
+
+impl Default for EdgeDetectionSettings {
+    fn default() -> Self {
+        EdgeDetectionSettings {
+            operator: EdgeOperator::Sobel,
+            depth_threshold: 0.05,
+            normal_threshold: 0.5,
+            line_width: 1.0,
+        }
+    }
+}
+
+/// End of the quad lines
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineCap {
+    Butt,
+    Square,
+    Round,
+}
+
+impl LineCap {
+    /// Value of the `u_cap` uniform
+    pub fn to_int(self) -> i32 {
+        match self {
+            LineCap::Butt => 0,
+            LineCap::Square => 1,
+            LineCap::Round => 2,
+        }
+    }
+
+    pub fn from_int(value : i32) -> LineCap {
+        match value {
+            0 => LineCap::Butt,
+            1 => LineCap::Square,
+            _ => LineCap::Round,
+        }
+    }
+}
+
+/// Edges expanded to screen-aligned quads, used by the multi pass and feature edge modes
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineSettings {
+    /// Draw the multi pass wireframe with quads instead of `glLineWidth` lines
+    pub quads : bool,
+    /// Width in pixels
+    pub width : f32,
+    pub cap : LineCap,
+    /// Minimum angle in degrees between the faces of a crease edge
+    pub crease_angle : f32,
+    /// Give boundary and crease edges their own color
+    pub color_by_category : bool,
+    pub boundary_color : [f32; 4],
+    pub crease_color : [f32; 4],
+}
+
+impl Default for LineSettings {
+    fn default() -> Self {
+        LineSettings {
+            quads: true,
+            width: 2.0,
+            cap: LineCap::Round,
+            crease_angle: 30.0,
+            color_by_category: false,
+            boundary_color: [0.9, 0.2, 0.1, 1.0],
+            crease_color: [0.1, 0.4, 0.9, 1.0],
+        }
+    }
+}
+
+/// Dash pattern of a stroke
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokePattern {
+    Solid,
+    Dashed,
+    Dotted,
+    DashDot,
+}
+
+impl StrokePattern {
+    pub fn to_int(self) -> i32 {
+        match self {
+            StrokePattern::Solid => 0,
+            StrokePattern::Dashed => 1,
+            StrokePattern::Dotted => 2,
+            StrokePattern::DashDot => 3,
+        }
+    }
+
+    pub fn from_int(value : i32) -> StrokePattern {
+        match value {
+            1 => StrokePattern::Dashed,
+            2 => StrokePattern::Dotted,
+            3 => StrokePattern::DashDot,
+            _ => StrokePattern::Solid,
+        }
+    }
+}
+
+/// Pattern of one line category, lengths in pixels along the line
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrokeStyle {
+    pub pattern : StrokePattern,
+    pub dash_length : f32,
+    pub gap_length : f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle {
+            pattern: StrokePattern::Solid,
+            dash_length: 8.0,
+            gap_length: 4.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    /// Value of the `u_stroke` uniforms: pattern, dash length, gap length and phase
+    pub fn uniform(&self, pixel_scale : f32, phase : f32) -> [f32; 4] {
+        [
+            self.pattern.to_int() as f32,
+            self.dash_length * pixel_scale,
+            self.gap_length * pixel_scale,
+            phase * pixel_scale,
+        ]
+    }
+}
+
+/// Stroke patterns per line category, as used in technical drawings
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrokeSettings {
+    // Plain values have to come before the tables, TOML can't write them afterwards
+    /// Draw the edges hidden behind the mesh, only the quad lines can
+    pub show_hidden : bool,
+    /// Movement of the patterns along the lines in pixels per second
+    pub animation_speed : f32,
+    pub visible : StrokeStyle,
+    pub hidden : StrokeStyle,
+    /// Edges between a face turned towards the camera and one turned away, quad lines only
+    pub silhouette : StrokeStyle,
+}
+
+impl Default for StrokeSettings {
+    fn default() -> Self {
+        StrokeSettings {
+            show_hidden: false,
+            animation_speed: 0.0,
+            visible: StrokeStyle::default(),
+            hidden: StrokeStyle {
+                pattern: StrokePattern::Dashed,
+                dash_length: 6.0,
+                gap_length: 4.0,
+            },
+            silhouette: StrokeStyle::default(),
+        }
+    }
+}
+
+/// Anti-aliasing of the rendered scene
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AntiAliasingSettings {
+    /// Samples per pixel of the scene render target, 1 renders straight to the window
+    pub msaa_samples : u32,
+    /// Turn the edge distance coverage into MSAA sample coverage, only has an effect with MSAA
+    pub alpha_to_coverage : bool,
+    /// Smooth the lines of the multi pass wireframe
+    pub line_smooth : bool,
+    /// Render the left half of the window without anti-aliasing
+    pub compare : bool,
+}
+
+impl Default for AntiAliasingSettings {
+    fn default() -> Self {
+        AntiAliasingSettings {
+            msaa_samples: 1,
+            alpha_to_coverage: false,
+            line_smooth: false,
+            compare: false,
+        }
+    }
+}
+
+/// Settings of the SVG/PDF line art export
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    /// File written by the export button, the extension selects SVG or PDF
+    pub path : String,
+    /// Remove the edges that are occluded by the mesh itself
+    pub hidden_line_removal : bool,
+    /// Stroke width in pixels for each unit of line thickness, used by the modes that
+    /// don't express the thickness in pixels
+    pub stroke_scale : f32,
+    /// Page color, nothing is drawn behind the lines when the alpha is zero
+    pub background_color : [f32; 4],
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            path: "wireframe.svg".to_string(),
+            hidden_line_removal: true,
+            stroke_scale: 100.0,
+            background_color: [1.0, 1.0, 1.0, 0.0],
+        }
+    }
+}
+
+impl ExportSettings {
+    /// Stroke width in pixels matching the line thickness of the render settings, for a target
+    /// `height` pixels high. `clip_w` is the clip space w of the mesh, the corrected single pass
+    /// thickness is a clip space distance and gets thinner further away from the camera.
+    pub fn stroke_width(&self, render : &RenderSettings, lines : &LineSettings, edges : &EdgeDetectionSettings, height : u32, clip_w : f32) -> f32 {
+        match render.mode {
+            WireframeMode::ScreenSpace => edges.line_width,
+            WireframeMode::MultiPass if lines.quads => lines.width,
+            WireframeMode::FeatureEdges => lines.width,
+            // Passed to glLineWidth as is
+            WireframeMode::MultiPass => render.line_thickness,
+            // The thickness is drawn on both triangles of an edge and one unit of clip space
+            // covers half the height after the divide by w
+            WireframeMode::SinglePassCorrection => render.line_thickness * height as f32 / clip_w.max(1e-4),
+            _ => render.line_thickness * self.stroke_scale,
+        }
+    }
+}
+
+/// Settings of the frame profiler
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilerSettings {
+    /// Measure the CPU and GPU time of every pass, the timer queries cost a little themselves
+    pub enabled : bool,
+    /// Frames kept for the graphs and the CSV export
+    pub history_length : usize,
+    pub csv_path : String,
+    /// Show the draw call, state change and GL object counters of the last frame
+    pub show_statistics : bool,
+}
+
+impl Default for ProfilerSettings {
+    fn default() -> Self {
+        ProfilerSettings {
+            enabled: false,
+            history_length: 300,
+            csv_path: "profile.csv".to_string(),
+            show_statistics: false,
+        }
+    }
+}
+
+/// Filtering of the OpenGL debug output
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugSettings {
+    /// Least severe messages that are logged
+    pub min_severity : Severity,
+    /// Message ids that are never logged, for known driver chatter
+    pub ignored_ids : Vec<u32>,
+    /// Fold repeated messages into one entry with a count
+    pub deduplicate : bool,
+    /// Report messages inside the call that caused them, slower but gives a useful call stack
+    pub synchronous : bool,
+    /// Trap into the debugger on errors, turns on synchronous output. Without a debugger the
+    /// process is ended, so only turn it on when running under one
+    pub break_on_error : bool,
+    pub show_console : bool,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        DebugSettings {
+            min_severity: Severity::Low,
+            ignored_ids: Vec::new(),
+            deduplicate: true,
+            synchronous: false,
+            break_on_error: false,
+            show_console: false,
+        }
+    }
+}
+
+impl DebugSettings {
+    pub fn synchronous_output(&self) -> bool {
+        self.synchronous || self.break_on_error
+    }
+}
+
+/// Settings of the screenshot command
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotSettings {
+    /// Directory the screenshots are written to
+    pub directory : String,
+    /// Multiple of the window resolution, captures above 1x are rendered offscreen
+    pub scale : u32,
+    /// Capture the IMGUI overlay as well, only possible at 1x
+    pub include_ui : bool,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        ScreenshotSettings {
+            directory: "screenshots".to_string(),
+            scale: 1,
+            include_ui: false,
+        }
+    }
+}
+
+/// Settings of the turntable recorder
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurntableSettings {
+    /// Animation file, or the base name of the numbered files of a PNG sequence
+    pub output : String,
+    pub format : TurntableFormat,
+    /// Frames for a full revolution
+    pub frames : u32,
+    /// Playback rate of the animated formats
+    pub fps : u32,
+    /// Output resolution, zero uses the window size
+    pub width : u32,
+    pub height : u32,
+    pub mode : WireframeMode,
+}
+
+impl Default for TurntableSettings {
+    fn default() -> Self {
+        TurntableSettings {
+            output: "turntable.gif".to_string(),
+            format: TurntableFormat::Gif,
+            frames: 72,
+            fps: 24,
+            width: 0,
+            height: 0,
+            mode: WireframeMode::SinglePass,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub eye : [f32; 3],
+    pub target : [f32; 3],
+    /// Vertical field of view in radians
+    pub fov : f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            eye: [4.0, 1.8, 4.0],
+            target: [0.0, 0.0, 0.0],
+            fov: 3.14 / 4.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn view_matrix(&self) -> na::Mat4 {
+        na::look_at(
+            &na::Vec3::new(self.eye[0], self.eye[1], self.eye[2]),
+            &na::Vec3::new(self.target[0], self.target[1], self.target[2]),
+            &na::Vec3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    /// Projection and view of an image with the given size
+    pub fn view_projection(&self, width : u32, height : u32) -> na::Mat4 {
+        let aspect = width as f32 / height.max(1) as f32;
+        na::Mat4::new_perspective(aspect, self.fov, 0.01, 1000.0) * self.view_matrix()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width : u32,
+    pub height : u32,
+    pub vsync : bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 800,
+            height: 600,
+            vsync: true,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mesh : String,
+    pub recent_files : Vec<String>,
+    pub window : WindowSettings,
+    pub camera : CameraSettings,
+    pub render : RenderSettings,
+    pub processing : ProcessingOptions,
+    pub vector_overlay : VectorOverlaySettings,
+    pub uv_view : UvViewSettings,
+    pub antialiasing : AntiAliasingSettings,
+    pub edge_detection : EdgeDetectionSettings,
+    pub lines : LineSettings,
+    pub strokes : StrokeSettings,
+    pub profiler : ProfilerSettings,
+    pub debug : DebugSettings,
+    pub export : ExportSettings,
+    pub screenshot : ScreenshotSettings,
+    pub turntable : TurntableSettings,
+
+    // Named render settings selectable from the UI
+    pub presets : BTreeMap<String, RenderSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut presets = BTreeMap::new();
+        presets.insert("Default".to_string(), RenderSettings::default());
+        presets.insert("Blueprint".to_string(), RenderSettings {
+            mode: WireframeMode::SinglePassCorrection,
+            line_thickness: 0.02,
+            falloff: 0.005,
+            solid_color: [0.09, 0.25, 0.55, 1.0],
+            line_color: [0.9, 0.95, 1.0, 1.0],
+            color_source: ColorSource::Solid,
+            heatmap_metric: HeatmapMetric::AspectRatio,
+        });
+        presets.insert("Clay".to_string(), RenderSettings {
+            mode: WireframeMode::SinglePass,
+            line_thickness: 0.01,
+            falloff: 0.003,
+            solid_color: [0.78, 0.62, 0.5, 1.0],
+            line_color: [0.25, 0.15, 0.1, 1.0],
+            color_source: ColorSource::Solid,
+            heatmap_metric: HeatmapMetric::AspectRatio,
+        });
+
+        Settings {
+            mesh: "assets/suzanne.obj".to_string(),
+            recent_files: Vec::new(),
+            window: WindowSettings::default(),
+            camera: CameraSettings::default(),
+            render: RenderSettings::default(),
+            processing: ProcessingOptions::default(),
+            vector_overlay: VectorOverlaySettings::default(),
+            uv_view: UvViewSettings::default(),
+            antialiasing: AntiAliasingSettings::default(),
+            edge_detection: EdgeDetectionSettings::default(),
+            lines: LineSettings::default(),
+            strokes: StrokeSettings::default(),
+            profiler: ProfilerSettings::default(),
+            debug: DebugSettings::default(),
+            export: ExportSettings::default(),
+            screenshot: ScreenshotSettings::default(),
+            turntable: TurntableSettings::default(),
+            presets,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from a TOML file
+    pub fn load(path : &Path) -> Result<Settings, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read \"{}\": {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse \"{}\": {}", path.display(), e))
+    }
+
+    /// Loads the settings from `path` when it exists, falls back to the defaults otherwise
+    pub fn load_or_default(path : &Path) -> Settings {
+        if !path.exists() {
+            return Settings::default();
+        }
+
+        match Settings::load(path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("{}, using default settings.", e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path : &Path) -> Result<(), String> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))
+    }
+
+    /// Moves the file to the front of the recent files list
+    pub fn add_recent_file(&mut self, path : &str) {
+        self.recent_files.retain(|p| p != path);
+        self.recent_files.insert(0, path.to_string());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Sorted names of all presets, used to populate the UI
+    pub fn preset_names(&self) -> Vec<String> {
+        self.presets.keys().cloned().collect()
+    }
+
+    /// Copies the preset into the active render settings. Returns false if the preset doesn't exist.
+    pub fn apply_preset(&mut self, name : &str) -> bool {
+        match self.presets.get(name) {
+            Some(preset) => {
+                self.render = preset.clone();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Stores the active render settings as a (new or existing) preset
+    pub fn store_preset(&mut self, name : &str) {
+        self.presets.insert(name.to_string(), self.render.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+
+    #[test]
+    fn save_and_load_defaults() {
+        let path = std::env::temp_dir().join(format!("wireframe_settings_{}.toml", std::process::id()));
+        let settings = Settings::default();
+        settings.save(&path).expect("Failed to save the default settings");
+        let loaded = Settings::load(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let loaded = loaded.expect("Failed to load the saved settings");
+        assert_eq!(toml::to_string(&loaded).unwrap(), toml::to_string(&settings).unwrap());
+    }
+}
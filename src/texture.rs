@@ -0,0 +1,75 @@
+/// 2D textures loaded from disk
+use gl::types::*;
+use std::path::Path;
+
+use crate::device::stats::{self, ObjectKind};
+
+pub struct Texture {
+    pub id : GLuint,
+    pub width : u32,
+    pub height : u32,
+}
+
+impl Texture {
+    /// Creates an RGBA8 texture from tightly packed pixels (first row is the top of the image)
+    pub fn from_rgba(width : u32, height : u32, pixels : &[u8]) -> Texture {
+        let mut id = 0;
+        unsafe {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+            stats::object_created(ObjectKind::Texture, 1);
+            gl::TextureStorage2D(id, 1, gl::RGBA8, width as GLsizei, height as GLsizei);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+            gl::TextureSubImage2D(id, 0, 0, 0, width as GLsizei, height as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const std::ffi::c_void);
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        }
+
+        Texture {
+            id,
+            width,
+            height,
+        }
+    }
+
+    /// Loads a PNG file, the image is flipped so uv (0,0) is the bottom left like in most DCC tools
+    pub fn load_png(path : &Path) -> Result<Texture, String> {
+        let error = |e : &dyn std::fmt::Display| format!("Failed to load \"{}\": {}", path.display(), e);
+
+        let file = std::fs::File::open(path).map_err(|e| error(&e))?;
+        let decoder = png::Decoder::new(file);
+        let (info, mut reader) = decoder.read_info().map_err(|e| error(&e))?;
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer).map_err(|e| error(&e))?;
+
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err(error(&"only 8-bit images are supported"));
+        }
+
+        // Expand to RGBA
+        let pixels : Vec<u8> = match info.color_type {
+            png::ColorType::RGBA => buffer,
+            png::ColorType::RGB => buffer.chunks(3).flat_map(|p| vec![p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|p| vec![p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&g| vec![g, g, g, 255]).collect(),
+            png::ColorType::Indexed => return Err(error(&"indexed images are not supported")),
+        };
+
+        // Flip the rows so the bottom row is uploaded first
+        let row_size = info.width as usize * 4;
+        let flipped : Vec<u8> = pixels.chunks(row_size).rev().flat_map(|row| row.iter().cloned()).collect();
+
+        Ok(Texture::from_rgba(info.width, info.height, &flipped))
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+            stats::object_deleted(ObjectKind::Texture, 1);
+        }
+    }
+}
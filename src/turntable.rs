@@ -0,0 +1,298 @@
+/// Turntable recorder
+/// ---
+/// Renders the model for a full revolution with a fixed rotation step per frame, so the result
+/// only depends on the settings. Frames are written as a numbered PNG sequence or streamed into
+/// an animated GIF or APNG as they are rendered, so only one frame is kept in memory.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurntableFormat {
+    PngSequence,
+    Gif,
+    Apng,
+}
+
+impl TurntableFormat {
+    pub fn to_int(self) -> i32 {
+        match self {
+            TurntableFormat::PngSequence => 0,
+            TurntableFormat::Gif => 1,
+            TurntableFormat::Apng => 2,
+        }
+    }
+
+    pub fn from_int(value : i32) -> TurntableFormat {
+        match value {
+            1 => TurntableFormat::Gif,
+            2 => TurntableFormat::Apng,
+            _ => TurntableFormat::PngSequence,
+        }
+    }
+
+    /// Format matching the extension of `path`: .gif, .apng or a PNG sequence otherwise
+    pub fn from_path(path : &Path) -> TurntableFormat {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()) {
+            Some(ref e) if e == "gif" => TurntableFormat::Gif,
+            Some(ref e) if e == "apng" => TurntableFormat::Apng,
+            _ => TurntableFormat::PngSequence,
+        }
+    }
+}
+
+/// Records the frames of a single turntable
+pub struct Recorder {
+    /// Snapshot of the settings when the recording started, the turntable mode is applied
+    pub settings : Settings,
+    pub width : u32,
+    pub height : u32,
+    output : PathBuf,
+    frame : u32,
+    /// Opened with the first frame
+    encoder : Option<Encoder>,
+}
+
+enum Encoder {
+    PngSequence,
+    Gif(GifWriter),
+    Apng(ApngWriter),
+}
+
+impl Recorder {
+    /// Starts a recording, a zero width or height in the turntable settings uses the window size
+    pub fn new(settings : &Settings, window_size : (u32, u32)) -> Recorder {
+        let turntable = &settings.turntable;
+        let mut settings = settings.clone();
+        settings.render.mode = turntable.mode;
+
+        Recorder {
+            width: if turntable.width > 0 { turntable.width } else { window_size.0 },
+            height: if turntable.height > 0 { turntable.height } else { window_size.1 },
+            output: PathBuf::from(&turntable.output),
+            frame: 0,
+            encoder: None,
+            settings,
+        }
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.settings.turntable.frames.max(1)
+    }
+
+    /// Rotation step between two frames, the last frame ends one step before a full revolution
+    pub fn time_step(&self) -> f32 {
+        2.0 * std::f32::consts::PI / self.frame_count() as f32
+    }
+
+    /// Value of `elapsed` (the model rotation) for the next frame
+    pub fn elapsed(&self) -> f32 {
+        self.frame as f32 * self.time_step()
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.frame as f32 / self.frame_count() as f32
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.frame_count()
+    }
+
+    /// Path of a frame of the PNG sequence: `<stem>_<frame>.png` next to the output
+    fn sequence_path(&self, frame : u32) -> PathBuf {
+        let stem = self.output.file_stem().and_then(|s| s.to_str()).unwrap_or("turntable");
+        self.output.with_file_name(format!("{}_{:04}.png", stem, frame))
+    }
+
+    fn open_encoder(&self) -> Result<Encoder, String> {
+        let fps = self.settings.turntable.fps.max(1);
+        Ok(match self.settings.turntable.format {
+            TurntableFormat::PngSequence => Encoder::PngSequence,
+            TurntableFormat::Gif => Encoder::Gif(GifWriter::new(&self.output, self.width, self.height, fps)?),
+            TurntableFormat::Apng => Encoder::Apng(ApngWriter::new(&self.output, self.width, self.height, self.frame_count(), fps)?),
+        })
+    }
+
+    /// Adds the top-down RGBA8 pixels of the next frame
+    pub fn add_frame(&mut self, mut pixels : Vec<u8>) -> Result<(), String> {
+        if self.encoder.is_none() {
+            self.encoder = Some(self.open_encoder()?);
+        }
+        let path = self.sequence_path(self.frame);
+        match self.encoder.as_mut().unwrap() {
+            Encoder::PngSequence => crate::capture::write_png(&path, self.width, self.height, &pixels)?,
+            Encoder::Gif(gif) => gif.write_frame(&mut pixels)?,
+            Encoder::Apng(apng) => apng.write_frame(&pixels)?,
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Completes the output file, returns a description of what was written
+    pub fn finish(self) -> Result<String, String> {
+        match self.encoder {
+            Some(Encoder::PngSequence) | None => Ok(format!("{} frames to \"{}\"", self.frame, self.sequence_path(0).display())),
+            Some(Encoder::Gif(gif)) => {
+                gif.finish();
+                Ok(format!("\"{}\"", self.output.display()))
+            },
+            Some(Encoder::Apng(apng)) => {
+                apng.finish()?;
+                Ok(format!("\"{}\"", self.output.display()))
+            },
+        }
+    }
+}
+
+fn write_error(path : &Path) -> impl Fn(std::io::Error) -> String + '_ {
+    move |e| format!("Failed to write \"{}\": {}", path.display(), e)
+}
+
+/// Writes a looping GIF one frame at a time, every frame gets its own quantized palette
+struct GifWriter {
+    encoder : gif::Encoder<BufWriter<File>>,
+    path : PathBuf,
+    width : u16,
+    height : u16,
+    fps : u32,
+    frame : u32,
+}
+
+impl GifWriter {
+    fn new(path : &Path, width : u32, height : u32, fps : u32) -> Result<GifWriter, String> {
+        use gif::SetParameter;
+
+        if width > u32::from(std::u16::MAX) || height > u32::from(std::u16::MAX) {
+            return Err(format!("{}x{} is too large for a GIF", width, height));
+        }
+        let file = File::create(path).map_err(write_error(path))?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(write_error(path))?;
+        encoder.set(gif::Repeat::Infinite).map_err(write_error(path))?;
+
+        Ok(GifWriter {
+            encoder,
+            path: path.to_path_buf(),
+            width: width as u16,
+            height: height as u16,
+            fps,
+            frame: 0,
+        })
+    }
+
+    fn write_frame(&mut self, pixels : &mut [u8]) -> Result<(), String> {
+        // GIF delays are in hundredths of a second. Each delay is the difference of the rounded
+        // down start times so the rounding error doesn't add up over the animation.
+        let start = self.frame * 100 / self.fps;
+        let end = (self.frame + 1) * 100 / self.fps;
+
+        let mut frame = gif::Frame::from_rgba_speed(self.width, self.height, pixels, 10);
+        frame.delay = (end - start).max(1) as u16;
+        self.encoder.write_frame(&frame).map_err(write_error(&self.path))?;
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// The encoder writes the trailer and flushes the file when it is dropped
+    fn finish(self) {}
+}
+
+fn write_chunk(out : &mut impl Write, kind : &[u8; 4], data : &[u8]) -> std::io::Result<()> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc.finalize().to_be_bytes())
+}
+
+/// Writes a looping animated PNG one frame at a time. The png crate we use can't write
+/// animations, so the chunks are written here; every frame covers the full image and replaces
+/// the previous one. The frame count goes into the header, so it has to be known up front.
+struct ApngWriter {
+    out : BufWriter<File>,
+    path : PathBuf,
+    width : u32,
+    height : u32,
+    fps : u32,
+    frame : u32,
+    sequence : u32,
+}
+
+impl ApngWriter {
+    fn new(path : &Path, width : u32, height : u32, frames : u32, fps : u32) -> Result<ApngWriter, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create \"{}\": {}", path.display(), e))?;
+        let mut out = BufWriter::new(file);
+        let error = write_error(path);
+
+        out.write_all(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]).map_err(&error)?;
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        // 8 bit RGBA, deflate, adaptive filtering, no interlacing
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut out, b"IHDR", &header).map_err(&error)?;
+
+        let mut animation = Vec::new();
+        animation.extend_from_slice(&frames.to_be_bytes());
+        animation.extend_from_slice(&0u32.to_be_bytes());
+        write_chunk(&mut out, b"acTL", &animation).map_err(&error)?;
+
+        Ok(ApngWriter {
+            out,
+            path: path.to_path_buf(),
+            width,
+            height,
+            fps,
+            frame: 0,
+            sequence: 0,
+        })
+    }
+
+    fn write_frame(&mut self, pixels : &[u8]) -> Result<(), String> {
+        let error = write_error(&self.path);
+
+        let mut control = Vec::new();
+        control.extend_from_slice(&self.sequence.to_be_bytes());
+        control.extend_from_slice(&self.width.to_be_bytes());
+        control.extend_from_slice(&self.height.to_be_bytes());
+        control.extend_from_slice(&0u32.to_be_bytes());
+        control.extend_from_slice(&0u32.to_be_bytes());
+        control.extend_from_slice(&1u16.to_be_bytes());
+        control.extend_from_slice(&(self.fps as u16).to_be_bytes());
+        control.extend_from_slice(&[0, 0]);
+        write_chunk(&mut self.out, b"fcTL", &control).map_err(&error)?;
+        self.sequence += 1;
+
+        // Every row starts with its filter type, 0 is none
+        let row_size = self.width as usize * 4;
+        let mut raw = Vec::with_capacity((row_size + 1) * self.height as usize);
+        for row in pixels.chunks(row_size) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        let compressed = deflate::deflate_bytes_zlib(&raw);
+
+        if self.frame == 0 {
+            write_chunk(&mut self.out, b"IDAT", &compressed).map_err(&error)?;
+        } else {
+            let mut data = Vec::with_capacity(compressed.len() + 4);
+            data.extend_from_slice(&self.sequence.to_be_bytes());
+            data.extend_from_slice(&compressed);
+            write_chunk(&mut self.out, b"fdAT", &data).map_err(&error)?;
+            self.sequence += 1;
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        let error = write_error(&self.path);
+        write_chunk(&mut self.out, b"IEND", &[]).map_err(&error)?;
+        self.out.flush().map_err(&error)
+    }
+}
@@ -0,0 +1,84 @@
+/// Console listing the debug output of the graphics API, newest message first
+use imgui::{im_str, ImString, Ui};
+
+use crate::device::debug::{DebugLogHandle, Severity};
+use crate::settings::DebugSettings;
+
+pub struct DebugConsole {
+    search : ImString,
+}
+
+fn severity_color(severity : Severity) -> (f32, f32, f32, f32) {
+    match severity {
+        Severity::High => (1.0, 0.35, 0.3, 1.0),
+        Severity::Medium => (1.0, 0.7, 0.25, 1.0),
+        Severity::Low => (0.95, 0.95, 0.5, 1.0),
+        Severity::Notification => (0.7, 0.7, 0.7, 1.0),
+    }
+}
+
+impl DebugConsole {
+    pub fn new() -> DebugConsole {
+        DebugConsole {
+            search: ImString::with_capacity(128),
+        }
+    }
+
+    /// Draws the filters and the messages, the filters are written to `settings`.
+    /// Returns true when the filters changed and have to be applied to the log.
+    pub fn draw(&mut self, ui : &Ui, log : &DebugLogHandle, settings : &mut DebugSettings) -> bool {
+        let mut severity = settings.min_severity.to_int() as i32;
+        let mut changed = ui.combo(im_str!("Min severity"), &mut severity, &[im_str!("Notification"), im_str!("Low"), im_str!("Medium"), im_str!("High")], 4);
+        settings.min_severity = Severity::from_int(severity.max(0) as u32);
+        changed |= ui.checkbox(im_str!("Fold repeated messages"), &mut settings.deduplicate);
+        changed |= ui.checkbox(im_str!("Synchronous output"), &mut settings.synchronous);
+        changed |= ui.checkbox(im_str!("Break on errors"), &mut settings.break_on_error);
+
+        if !settings.ignored_ids.is_empty() {
+            let ids : Vec<String> = settings.ignored_ids.iter().map(|id| id.to_string()).collect();
+            ui.text(format!("Ignored ids: {}", ids.join(", ")));
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Reset")) {
+                settings.ignored_ids.clear();
+                changed = true;
+            }
+        }
+
+        let mut log = match log.lock() {
+            Ok(log) => log,
+            Err(_) => return changed,
+        };
+        if ui.small_button(im_str!("Clear")) {
+            log.clear();
+        }
+        ui.same_line(0.0);
+        ui.text(format!("{} messages, {} filtered", log.messages().len(), log.filtered()));
+        ui.input_text(im_str!("Search"), &mut self.search).build();
+
+        let search = self.search.to_str().to_lowercase();
+        let mut ignore = None;
+        ui.child_frame(im_str!("debug_console_messages"), (0.0, 250.0))
+            .show_borders(true)
+            .build(|| {
+                for (i, message) in log.messages().iter().enumerate().rev() {
+                    let line = format!("{}x {}", message.count, message);
+                    if !search.is_empty() && !line.to_lowercase().contains(&search) {
+                        continue;
+                    }
+                    if ui.small_button(&ImString::new(format!("Ignore##{}", i))) {
+                        ignore = Some(message.id);
+                    }
+                    ui.same_line(0.0);
+                    ui.text_colored(severity_color(message.severity), &ImString::new(line));
+                }
+            });
+
+        if let Some(id) = ignore {
+            if !settings.ignored_ids.contains(&id) {
+                settings.ignored_ids.push(id);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
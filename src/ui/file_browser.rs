@@ -0,0 +1,100 @@
+/// Simple directory browser to pick model files from disk
+use std::path::{Path, PathBuf};
+
+use imgui::{ImGuiSelectableFlags, ImString, Ui, im_str};
+
+struct Entry {
+    label : ImString,
+    path : PathBuf,
+    is_dir : bool,
+}
+
+pub struct FileBrowser {
+    current_dir : PathBuf,
+    entries : Vec<Entry>,
+}
+
+impl FileBrowser {
+    pub fn new(dir : &Path) -> FileBrowser {
+        let mut browser = FileBrowser {
+            current_dir: dir.to_path_buf(),
+            entries: Vec::new(),
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Re-reads the current directory, only directories and supported model files are listed
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+
+        let read_dir = match std::fs::read_dir(&self.current_dir) {
+            Ok(r) => r,
+            Err(e) => {
+                println!("Failed to read \"{}\": {}", self.current_dir.display(), e);
+                return;
+            }
+        };
+
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            if !is_dir && !crate::mesh::is_supported(&path) {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let label = if is_dir { format!("[{}]", name) } else { name };
+            self.entries.push(Entry {
+                label: ImString::new(label),
+                path,
+                is_dir,
+            });
+        }
+
+        // Directories first, then alphabetical
+        self.entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.label.to_str().cmp(b.label.to_str())));
+    }
+
+    fn change_dir(&mut self, dir : PathBuf) {
+        self.current_dir = dir;
+        self.refresh();
+    }
+
+    /// Draws the browser, returns the file the user picked this frame
+    pub fn draw(&mut self, ui : &Ui) -> Option<PathBuf> {
+        let mut picked = None;
+        let mut next_dir = None;
+
+        ui.text(format!("{}", self.current_dir.display()));
+        if ui.small_button(im_str!("Up")) {
+            next_dir = self.current_dir.parent().map(|p| p.to_path_buf());
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Refresh")) {
+            self.refresh();
+        }
+
+        let entries = &self.entries;
+        ui.child_frame(im_str!("file_browser_entries"), (0.0, 150.0))
+            .show_borders(true)
+            .build(|| {
+                for entry in entries {
+                    if ui.selectable(&entry.label, false, ImGuiSelectableFlags::empty(), (0.0, 0.0)) {
+                        if entry.is_dir {
+                            next_dir = Some(entry.path.clone());
+                        } else {
+                            picked = Some(entry.path.clone());
+                        }
+                    }
+                }
+            });
+        ui.text_wrapped(&ImString::new(format!("Listing {} files. Drop other formats on the window to open them.", crate::mesh::SUPPORTED_EXTENSIONS.join(", "))));
+
+        if let Some(dir) = next_dir {
+            self.change_dir(dir);
+        }
+
+        picked
+    }
+}
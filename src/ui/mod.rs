@@ -0,0 +1,85 @@
+/// Reusable IMGUI panels used by the application
+pub mod uniform_editor;
+pub mod file_browser;
+pub mod debug_console;
+
+use imgui::Ui;
+
+use crate::mesh::ImportStats;
+use crate::mesh_analysis::{MeshAnalysis, Range};
+use crate::settings::{StrokePattern, StrokeStyle};
+
+/// Shows the statistics of the last mesh import
+pub fn import_stats(ui : &Ui, stats : &ImportStats) {
+    ui.text(format!("File: {}", stats.path));
+    ui.text(format!("Meshes: {}", stats.meshes));
+    ui.text(format!("Vertices: {}", stats.vertices));
+    ui.text(format!("Faces: {} ({} triangles)", stats.faces, stats.triangles));
+    ui.text(format!("UV sets: {}, vertex colors: {}", stats.uv_sets, yes_no(stats.has_colors)));
+    ui.text(format!("Generated normals: {}, tangents: {}", yes_no(stats.generated_normals), yes_no(stats.generated_tangents)));
+    ui.text(format!("Welding: {} -> {} vertices", stats.vertices_before_weld, stats.vertices_after_weld));
+    ui.text(format!("ACMR: {:.3} -> {:.3}", stats.acmr_before, stats.acmr_after));
+    ui.text(format!("Load time: {}ms{}", stats.load_time_ms, if stats.from_cache { " (cached)" } else { "" }));
+}
+
+/// Shows the quality metrics of the mesh as a table
+pub fn mesh_analysis(ui : &Ui, analysis : &MeshAnalysis) {
+    use imgui::im_str;
+
+    ui.columns(4, im_str!("metric_ranges"), true);
+    for header in ["Metric", "Min", "Avg", "Max"].iter() {
+        ui.text(header);
+        ui.next_column();
+    }
+    ui.separator();
+
+    let rows : [(&str, &Range); 3] = [
+        ("Aspect ratio", &analysis.aspect_ratio),
+        ("Min angle", &analysis.min_angle),
+        ("Area", &analysis.area),
+    ];
+    for (name, range) in rows.iter() {
+        ui.text(name);
+        ui.next_column();
+        for value in [range.min, range.avg, range.max].iter() {
+            ui.text(format!("{:.4}", value));
+            ui.next_column();
+        }
+    }
+    ui.columns(1, im_str!("metric_ranges_end"), false);
+    ui.separator();
+
+    ui.columns(2, im_str!("metric_counts"), true);
+    let counts = [
+        (format!("Skinny triangles (< {} deg)", crate::mesh_analysis::SKINNY_ANGLE), analysis.skinny_triangles),
+        ("Degenerate triangles".to_string(), analysis.degenerate_triangles),
+        ("Flipped normals".to_string(), analysis.flipped_triangles),
+        ("Duplicate vertices".to_string(), analysis.duplicate_vertices),
+        ("Boundary edges".to_string(), analysis.boundary_edges),
+        ("Non-manifold edges".to_string(), analysis.non_manifold_edges),
+    ];
+    for (name, count) in counts.iter() {
+        ui.text(name);
+        ui.next_column();
+        ui.text(format!("{}", count));
+        ui.next_column();
+    }
+    ui.columns(1, im_str!("metric_counts_end"), false);
+}
+
+fn yes_no(value : bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/// Edits a stroke pattern, `id` keeps the widgets of several styles apart
+pub fn stroke_style(ui : &Ui, id : &str, style : &mut StrokeStyle) {
+    use imgui::{im_str, ImString};
+
+    let mut pattern = style.pattern.to_int();
+    ui.combo(&ImString::new(format!("Pattern##{}", id)), &mut pattern, &[im_str!("Solid"), im_str!("Dashed"), im_str!("Dotted"), im_str!("Dash dot")], 10);
+    style.pattern = StrokePattern::from_int(pattern);
+    if style.pattern != StrokePattern::Solid {
+        ui.slider_float(&ImString::new(format!("Dash length##{}", id)), &mut style.dash_length, 1.0, 64.0).build();
+        ui.slider_float(&ImString::new(format!("Gap length##{}", id)), &mut style.gap_length, 1.0, 64.0).build();
+    }
+}
@@ -0,0 +1,100 @@
+/// Automatically generated editor for the uniforms declared by a pipeline's shaders
+use imgui::{ImString, Ui};
+
+use crate::pipeline::{Pipeline, ShaderUniform};
+
+/// Draws a widget for every editable uniform on the pipeline under a header with its label and
+/// writes back changed values. The widget ids are scoped by the label, so several pipelines can
+/// share one window.
+/// Uniforms annotated with `@internal` are driven by the application and are skipped.
+///
+/// - `float`: slider when a `@range(min, max)` annotation is present, input field otherwise
+/// - `vec3`/`vec4` named `*_color`: color picker
+/// - `bool` and `int` without a range: checkbox
+///
+/// Hovering a widget shows the default declared in the shader.
+pub fn draw(ui : &Ui, pipeline : &Pipeline) {
+    ui.text(pipeline.label());
+    ui.push_id(pipeline.label());
+
+    for info in pipeline.uniforms() {
+        if info.internal {
+            continue;
+        }
+
+        let label = ImString::new(info.name.clone());
+        let is_color = info.name.ends_with("_color");
+
+        let new_value = match info.value {
+            ShaderUniform::Bool(mut v) => {
+                if ui.checkbox(&label, &mut v) { Some(ShaderUniform::Bool(v)) } else { None }
+            },
+            ShaderUniform::Int(v) => {
+                match info.range {
+                    Some((min, max)) => {
+                        let mut v = v;
+                        if ui.slider_int(&label, &mut v, min as i32, max as i32).build() { Some(ShaderUniform::Int(v)) } else { None }
+                    },
+                    None => {
+                        let mut flag = v != 0;
+                        if ui.checkbox(&label, &mut flag) { Some(ShaderUniform::Int(flag as i32)) } else { None }
+                    }
+                }
+            },
+            ShaderUniform::Float(mut v) => {
+                let changed = match info.range {
+                    Some((min, max)) => ui.slider_float(&label, &mut v, min, max).build(),
+                    None => ui.input_float(&label, &mut v).build(),
+                };
+                if changed { Some(ShaderUniform::Float(v)) } else { None }
+            },
+            ShaderUniform::Float2(mut v) => {
+                if ui.input_float2(&label, &mut v).build() { Some(ShaderUniform::Float2(v)) } else { None }
+            },
+            ShaderUniform::Float3(mut v) => {
+                let changed = if is_color {
+                    ui.color_edit(&label, &mut v).build()
+                } else {
+                    ui.input_float3(&label, &mut v).build()
+                };
+                if changed { Some(ShaderUniform::Float3(v)) } else { None }
+            },
+            ShaderUniform::Float4(mut v) => {
+                let changed = if is_color {
+                    ui.color_edit(&label, &mut v).build()
+                } else {
+                    ui.input_float4(&label, &mut v).build()
+                };
+                if changed { Some(ShaderUniform::Float4(v)) } else { None }
+            },
+            // Matrices and samplers are not user editable
+            _ => continue,
+        };
+
+        if ui.is_item_hovered() {
+            ui.tooltip_text(format!("Default: {}", format_value(info.default)));
+        }
+
+        if let Some(v) = new_value {
+            pipeline.set_uniform(&info.name, v);
+        }
+    }
+
+    if ui.small_button(imgui::im_str!("Reset to defaults")) {
+        pipeline.reset_uniforms();
+    }
+
+    ui.pop_id();
+}
+
+fn format_value(value : ShaderUniform) -> String {
+    match value {
+        ShaderUniform::Bool(v) => format!("{}", v),
+        ShaderUniform::Int(v) => format!("{}", v),
+        ShaderUniform::Float(v) => format!("{}", v),
+        ShaderUniform::Float2(v) => format!("{:?}", v),
+        ShaderUniform::Float3(v) => format!("{:?}", v),
+        ShaderUniform::Float4(v) => format!("{:?}", v),
+        _ => String::new(),
+    }
+}
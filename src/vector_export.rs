@@ -0,0 +1,261 @@
+/// Vector line art export
+/// ---
+/// Projects the edges of the mesh with the current camera and writes them as SVG or PDF.
+/// Hidden lines are removed on the CPU: the triangles are rasterized into a depth buffer at the
+/// output resolution and every edge is sampled against it, only the visible runs are kept.
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::mesh::GlVert;
+use crate::mesh_edges;
+
+/// Vertices closer to the camera plane than this (clip space w) are not exported
+const NEAR_W : f32 = 1e-4;
+/// Relative depth tolerance, edges lie exactly on the triangles that occlude the rest
+const DEPTH_BIAS : f32 = 5e-3;
+/// Edge samples per pixel
+const SAMPLES_PER_PIXEL : f32 = 2.0;
+
+/// A visible line in output coordinates (pixels, y pointing down)
+#[derive(Clone, Copy)]
+pub struct Segment {
+    pub from : [f32; 2],
+    pub to : [f32; 2],
+}
+
+/// How the lines are drawn
+pub struct Style {
+    pub stroke_width : f32,
+    pub color : [f32; 4],
+    /// Page color, skipped when the alpha is zero
+    pub background : [f32; 4],
+}
+
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x : f32,
+    y : f32,
+    /// 1 / clip space w, interpolates linearly in screen space
+    inv_w : f32,
+}
+
+fn project(vertices : &[GlVert], model_view_projection : &na::Mat4, width : u32, height : u32) -> Vec<Option<ScreenVertex>> {
+    vertices.iter().map(|v| {
+        let clip = model_view_projection * na::Vec4::new(v.pos[0], v.pos[1], v.pos[2], 1.0);
+        if clip.w <= NEAR_W {
+            return None;
+        }
+        let inv_w = 1.0 / clip.w;
+        Some(ScreenVertex {
+            x: (clip.x * inv_w * 0.5 + 0.5) * width as f32,
+            y: (0.5 - clip.y * inv_w * 0.5) * height as f32,
+            inv_w,
+        })
+    }).collect()
+}
+
+/// Depth buffer holding the closest 1/w per pixel, 0 means empty
+struct DepthBuffer {
+    width : usize,
+    height : usize,
+    data : Vec<f32>,
+}
+
+impl DepthBuffer {
+    fn new(width : u32, height : u32) -> DepthBuffer {
+        DepthBuffer {
+            width: width as usize,
+            height: height as usize,
+            data: vec![0.0; width as usize * height as usize],
+        }
+    }
+
+    fn rasterize(&mut self, a : ScreenVertex, b : ScreenVertex, c : ScreenVertex) {
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area.abs() < 1e-12 {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as usize).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as usize).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let w0 = ((b.x - px) * (c.y - py) - (b.y - py) * (c.x - px)) / area;
+                let w1 = ((c.x - px) * (a.y - py) - (c.y - py) * (a.x - px)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let inv_w = w0 * a.inv_w + w1 * b.inv_w + w2 * c.inv_w;
+                let depth = &mut self.data[y * self.width + x];
+                *depth = depth.max(inv_w);
+            }
+        }
+    }
+
+    /// Whether a point with the given 1/w is in front of the surface around the pixel.
+    /// The furthest depth of the neighbourhood is used so silhouettes stay visible.
+    fn visible(&self, x : f32, y : f32, inv_w : f32) -> bool {
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return false;
+        }
+
+        let (cx, cy) = (x as usize, y as usize);
+        let mut furthest = std::f32::MAX;
+        for ny in cy.saturating_sub(1)..(cy + 2).min(self.height) {
+            for nx in cx.saturating_sub(1)..(cx + 2).min(self.width) {
+                furthest = furthest.min(self.data[ny * self.width + nx]);
+            }
+        }
+        inv_w >= furthest * (1.0 - DEPTH_BIAS)
+    }
+}
+
+/// Projects the edges of the mesh to a `width` x `height` image, optionally removing hidden lines
+pub fn project_edges(vertices : &[GlVert], indices : &[u32], model_view_projection : &na::Mat4, width : u32, height : u32, hidden_line_removal : bool) -> Vec<Segment> {
+    let projected = project(vertices, model_view_projection, width, height);
+    let edges : Vec<(u32, u32)> = mesh_edges::extract(vertices, indices).iter().map(|e| (e.a, e.b)).collect();
+
+    if !hidden_line_removal {
+        return edges.iter().filter_map(|&(a, b)| {
+            let (a, b) = (projected[a as usize]?, projected[b as usize]?);
+            Some(Segment { from: [a.x, a.y], to: [b.x, b.y] })
+        }).collect();
+    }
+
+    let mut depth = DepthBuffer::new(width, height);
+    for tri in indices.chunks(3).filter(|t| t.len() == 3) {
+        if let (Some(a), Some(b), Some(c)) = (projected[tri[0] as usize], projected[tri[1] as usize], projected[tri[2] as usize]) {
+            depth.rasterize(a, b, c);
+        }
+    }
+
+    let mut segments = Vec::new();
+    for &(a, b) in edges.iter() {
+        let (a, b) = match (projected[a as usize], projected[b as usize]) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+
+        let length = ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt();
+        let steps = (length * SAMPLES_PER_PIXEL).ceil().max(1.0) as usize;
+        let point = |t : f32| [a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t];
+
+        // Collect the runs of visible samples
+        let mut run_start : Option<f32> = None;
+        let mut last_visible = 0.0;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let p = point(t);
+            let inv_w = a.inv_w + (b.inv_w - a.inv_w) * t;
+            if depth.visible(p[0], p[1], inv_w) {
+                if run_start.is_none() {
+                    run_start = Some(t);
+                }
+                last_visible = t;
+            } else if let Some(start) = run_start.take() {
+                if last_visible > start {
+                    segments.push(Segment { from: point(start), to: point(last_visible) });
+                }
+            }
+        }
+        if let Some(start) = run_start {
+            if last_visible > start {
+                segments.push(Segment { from: point(start), to: point(last_visible) });
+            }
+        }
+    }
+    segments
+}
+
+fn rgb_255(color : [f32; 4]) -> (u8, u8, u8) {
+    let c = |v : f32| (v.max(0.0).min(1.0) * 255.0).round() as u8;
+    (c(color[0]), c(color[1]), c(color[2]))
+}
+
+/// Builds an SVG document of the segments
+pub fn to_svg(width : u32, height : u32, segments : &[Segment], style : &Style) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#, width, height);
+
+    if style.background[3] > 0.0 {
+        let (r, g, b) = rgb_255(style.background);
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="rgb({},{},{})" fill-opacity="{}"/>"#, r, g, b, style.background[3]);
+    }
+
+    let (r, g, b) = rgb_255(style.color);
+    let _ = writeln!(
+        svg,
+        r#"<g fill="none" stroke="rgb({},{},{})" stroke-opacity="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round">"#,
+        r, g, b, style.color[3], style.stroke_width
+    );
+    let _ = writeln!(svg, r#"<path d=""#);
+    for s in segments {
+        let _ = writeln!(svg, "M{:.2} {:.2}L{:.2} {:.2}", s.from[0], s.from[1], s.to[0], s.to[1]);
+    }
+    let _ = writeln!(svg, r#""/>"#);
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// Builds a single page PDF of the segments, one pixel maps to one point.
+/// PDF has no simple stroke opacity, the alpha of the colors is ignored.
+pub fn to_pdf(width : u32, height : u32, segments : &[Segment], style : &Style) -> Vec<u8> {
+    let h = height as f32;
+
+    let mut content = String::new();
+    if style.background[3] > 0.0 {
+        let c = style.background;
+        let _ = writeln!(content, "{} {} {} rg 0 0 {} {} re f", c[0], c[1], c[2], width, height);
+    }
+    let c = style.color;
+    let _ = writeln!(content, "{} w 1 J 1 j {} {} {} RG", style.stroke_width, c[0], c[1], c[2]);
+    for s in segments {
+        let _ = writeln!(content, "{:.2} {:.2} m {:.2} {:.2} l S", s.from[0], h - s.from[1], s.to[0], h - s.to[1]);
+    }
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R /Resources << >> >>", width, height),
+        format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+    }
+
+    let xref = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = write!(pdf, "{:010} 00000 n \n", offset);
+    }
+    let _ = write!(pdf, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+    pdf.into_bytes()
+}
+
+/// Writes the segments to `path`, a `.pdf` extension writes a PDF and anything else an SVG
+pub fn write(path : &Path, width : u32, height : u32, segments : &[Segment], style : &Style) -> Result<(), String> {
+    let is_pdf = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+
+    let data = if is_pdf {
+        to_pdf(width, height, segments, style)
+    } else {
+        to_svg(width, height, segments, style).into_bytes()
+    };
+    std::fs::write(path, data).map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))
+}